@@ -1,16 +1,50 @@
 #![no_main]
 
-use crate::addr::K8sResourceAddress;
+use std::path::{Component, Path};
+
+use autoschematic_connector_k8s::addr::{AddressScope, K8sClusterAddress, K8sResourceAddress};
 use autoschematic_core::connector::ResourceAddress;
 use libfuzzer_sys::fuzz_target;
 
-#[cfg(fuzzing)]
-fuzz_target!(|addr: K8sResourceAddress| {
+/// Whether every `/`-separated segment of a path survives as a normal path component.
+/// Addresses with empty names, `.`/`..`, or names containing `/` can't be represented as paths at all.
+fn is_representable(path: &Path) -> bool {
+    let Some(s) = path.to_str() else { return false };
+    let segments: Vec<&str> = s.split('/').collect();
+    let components: Vec<Component> = path.components().collect();
+    segments.len() == components.len()
+        && segments
+            .iter()
+            .zip(&components)
+            .all(|(segment, component)| matches!(component, Component::Normal(c) if c.to_str() == Some(*segment)))
+}
+
+fuzz_target!(|addr: K8sClusterAddress| {
+    // `k8s/<cluster>/ns/...` is always a namespaced path, so a cluster-scoped dynamic kind can't use group "ns".
+    if let K8sResourceAddress::ClusterDynamic(group, ..) = &addr.res_addr {
+        if group == "ns" {
+            return;
+        }
+    }
+
     let path = addr.to_path_buf();
-    let Ok(Some(new_addr)) = K8sResourceAddress::from_path(&path) else {
+    if !is_representable(&path) {
         return;
+    }
+
+    let in_namespace_dir = path.components().nth(2).and_then(|c| c.as_os_str().to_str()) == Some("ns");
+    assert_eq!(
+        in_namespace_dir,
+        matches!(addr.res_addr.scope(), AddressScope::Namespaced(_)),
+        "{:?} is filed at the wrong scope: {:?}",
+        addr,
+        path
+    );
+
+    let new_addr = match K8sClusterAddress::from_path(&path) {
+        Ok(new_addr) => new_addr,
+        Err(e) => panic!("{:?} -> {:?} does not parse: {}", addr, path, e),
     };
-    println!("{:?} -> {:?} -> {:?}", addr, path, new_addr);
 
-    assert_eq!(addr, new_addr);
+    assert_eq!(addr, new_addr, "{:?} -> {:?} -> {:?}", addr, path, new_addr);
 });
@@ -0,0 +1,23 @@
+#![no_main]
+
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+
+use autoschematic_connector_k8s::addr::K8sClusterAddress;
+use autoschematic_core::connector::ResourceAddress;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let path = Path::new(OsStr::from_bytes(data));
+
+    // Any path that parses must map back to a path that parses to the same address.
+    let Ok(addr) = K8sClusterAddress::from_path(path) else {
+        return;
+    };
+    let new_path = addr.to_path_buf();
+    let new_addr = match K8sClusterAddress::from_path(&new_path) {
+        Ok(new_addr) => new_addr,
+        Err(e) => panic!("{:?} -> {:?} -> {:?} does not parse: {}", path, addr, new_path, e),
+    };
+
+    assert_eq!(addr, new_addr, "{:?} -> {:?} -> {:?} -> {:?}", path, addr, new_path, new_addr);
+});
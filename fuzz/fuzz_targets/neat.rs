@@ -0,0 +1,17 @@
+#![no_main]
+
+use autoschematic_connector_k8s::neat::neatify_resource;
+use libfuzzer_sys::fuzz_target;
+use serde_yaml::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(s) = std::str::from_utf8(data) else { return };
+    let Ok(mut v) = serde_yaml::from_str::<Value>(s) else { return };
+
+    neatify_resource(&mut v);
+
+    // Neatifying must be idempotent, or every import would show a diff against the last one.
+    let mut again = v.clone();
+    neatify_resource(&mut again);
+    assert_eq!(v, again);
+});
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
 
 #[cfg(feature = "fuzz")]
 use arbitrary::Arbitrary;
@@ -9,32 +12,172 @@ use autoschematic_core::{
 
 type Namespace = String;
 type Name = String;
+type Group = String;
+type Version = String;
+type Kind = String;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "fuzz", derive(Arbitrary))]
 pub enum K8sResourceAddress {
+    // Namespaced kinds, filed under `k8s/<cluster>/ns/<namespace>/`.
     Namespace(Namespace),
     Pod(Namespace, Name),
     Service(Namespace, Name),
     Deployment(Namespace, Name),
+    StatefulSet(Namespace, Name),
+    DaemonSet(Namespace, Name),
+    ReplicaSet(Namespace, Name),
+    Job(Namespace, Name),
+    CronJob(Namespace, Name),
     ConfigMap(Namespace, Name),
-    // Secret(Namespace, Name),
+    Ingress(Namespace, Name),
+    NetworkPolicy(Namespace, Name),
+    ResourceQuota(Namespace, Name),
+    LimitRange(Namespace, Name),
+    ServiceAccount(Namespace, Name),
+    HorizontalPodAutoscaler(Namespace, Name),
+    PodDisruptionBudget(Namespace, Name),
+    Secret(Namespace, Name),
     PersistentVolumeClaim(Namespace, Name),
-    PersistentVolume(Namespace),
     Role(Namespace, Name),
     RoleBinding(Namespace, Name),
+
+    // Cluster-scoped kinds, filed under `k8s/<cluster>/`.
+    PersistentVolume(Name),
     ClusterRole(Name),
     ClusterRoleBinding(Name),
+    IngressClass(Name),
+    StorageClass(Name),
+    CSIDriver(Name),
+    ValidatingWebhookConfiguration(Name),
+    MutatingWebhookConfiguration(Name),
+    ValidatingAdmissionPolicy(Name),
+    ValidatingAdmissionPolicyBinding(Name),
+    PriorityClass(Name),
+    RuntimeClass(Name),
+    /// Only the labels, annotations and taints owned by autoschematic's field manager.
+    Node(Name),
+    CustomResourceDefinition(Name),
+
+    /// A multi-document file of objects of any kind, each planned as if it were its own file.
+    Bundle(Name),
+
+    /// Any other namespaced kind, addressed by API group, version and kind, and resolved
+    /// through API discovery. The core group is written as "core", and kinds match case-insensitively.
+    Dynamic(Namespace, Group, Version, Kind, Name),
+    /// Any other cluster-scoped kind, addressed by API group, version and kind.
+    ClusterDynamic(Group, Version, Kind, Name),
 
     // Binding(Namespace, Name),
     // Endpoints(Namespace, Name),
-    // LimitRange(Namespace, Name),
-    // Node(Namespace, Name),
 
     // PodTemplate(Namespace, Name),
     // ReplicationController(Namespace, Name),
-    // ResourceQuota(Namespace, Name),
-    // ServiceAccount(Namespace, Name),
+}
+
+/// Whether an address is filed under a namespace or at cluster scope.
+/// A Namespace's own file lives in its namespace directory, so it counts as namespaced here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressScope<'a> {
+    Cluster,
+    Namespaced(&'a str),
+}
+
+impl K8sResourceAddress {
+    pub fn scope(&self) -> AddressScope<'_> {
+        self.path_parts().0
+    }
+
+    /// The scope, kind directory and file stem that make up an address's path.
+    /// Every variant declares its scope here, and `to_path_buf` builds the path from it.
+    fn path_parts(&self) -> (AddressScope<'_>, Option<Cow<'static, str>>, &str) {
+        match self {
+            K8sResourceAddress::Namespace(namespace) => (AddressScope::Namespaced(namespace), None, "ns"),
+            K8sResourceAddress::Pod(namespace, name) => (AddressScope::Namespaced(namespace), Some("pod".into()), name),
+            K8sResourceAddress::Service(namespace, name) => (AddressScope::Namespaced(namespace), Some("service".into()), name),
+            K8sResourceAddress::Deployment(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("deployment".into()), name)
+            }
+            K8sResourceAddress::StatefulSet(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("statefulset".into()), name)
+            }
+            K8sResourceAddress::DaemonSet(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("daemonset".into()), name)
+            }
+            K8sResourceAddress::ReplicaSet(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("replicaset".into()), name)
+            }
+            K8sResourceAddress::Job(namespace, name) => (AddressScope::Namespaced(namespace), Some("job".into()), name),
+            K8sResourceAddress::CronJob(namespace, name) => (AddressScope::Namespaced(namespace), Some("cronjob".into()), name),
+            K8sResourceAddress::ConfigMap(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("configmap".into()), name)
+            }
+            K8sResourceAddress::Ingress(namespace, name) => (AddressScope::Namespaced(namespace), Some("ingress".into()), name),
+            K8sResourceAddress::NetworkPolicy(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("networkpolicy".into()), name)
+            }
+            K8sResourceAddress::ResourceQuota(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("resourcequota".into()), name)
+            }
+            K8sResourceAddress::LimitRange(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("limitrange".into()), name)
+            }
+            K8sResourceAddress::ServiceAccount(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("serviceaccount".into()), name)
+            }
+            K8sResourceAddress::HorizontalPodAutoscaler(namespace, name) => (
+                AddressScope::Namespaced(namespace),
+                Some("horizontalpodautoscaler".into()),
+                name,
+            ),
+            K8sResourceAddress::PodDisruptionBudget(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("poddisruptionbudget".into()), name)
+            }
+            K8sResourceAddress::Secret(namespace, name) => (AddressScope::Namespaced(namespace), Some("secret".into()), name),
+            K8sResourceAddress::PersistentVolumeClaim(namespace, name) => (
+                AddressScope::Namespaced(namespace),
+                Some("persistentvolumeclaim".into()),
+                name,
+            ),
+            K8sResourceAddress::Role(namespace, name) => (AddressScope::Namespaced(namespace), Some("role".into()), name),
+            K8sResourceAddress::RoleBinding(namespace, name) => {
+                (AddressScope::Namespaced(namespace), Some("rolebinding".into()), name)
+            }
+            K8sResourceAddress::PersistentVolume(name) => (AddressScope::Cluster, Some("persistentvolume".into()), name),
+            K8sResourceAddress::ClusterRole(name) => (AddressScope::Cluster, Some("clusterrole".into()), name),
+            K8sResourceAddress::ClusterRoleBinding(name) => (AddressScope::Cluster, Some("clusterrolebinding".into()), name),
+            K8sResourceAddress::IngressClass(name) => (AddressScope::Cluster, Some("ingressclass".into()), name),
+            K8sResourceAddress::StorageClass(name) => (AddressScope::Cluster, Some("storageclass".into()), name),
+            K8sResourceAddress::CSIDriver(name) => (AddressScope::Cluster, Some("csidriver".into()), name),
+            K8sResourceAddress::ValidatingWebhookConfiguration(name) => {
+                (AddressScope::Cluster, Some("validatingwebhookconfiguration".into()), name)
+            }
+            K8sResourceAddress::MutatingWebhookConfiguration(name) => {
+                (AddressScope::Cluster, Some("mutatingwebhookconfiguration".into()), name)
+            }
+            K8sResourceAddress::ValidatingAdmissionPolicy(name) => {
+                (AddressScope::Cluster, Some("validatingadmissionpolicy".into()), name)
+            }
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(name) => {
+                (AddressScope::Cluster, Some("validatingadmissionpolicybinding".into()), name)
+            }
+            K8sResourceAddress::PriorityClass(name) => (AddressScope::Cluster, Some("priorityclass".into()), name),
+            K8sResourceAddress::RuntimeClass(name) => (AddressScope::Cluster, Some("runtimeclass".into()), name),
+            K8sResourceAddress::Node(name) => (AddressScope::Cluster, Some("node".into()), name),
+            K8sResourceAddress::CustomResourceDefinition(name) => {
+                (AddressScope::Cluster, Some("customresourcedefinition".into()), name)
+            }
+            K8sResourceAddress::Bundle(name) => (AddressScope::Cluster, Some("bundle".into()), name),
+            K8sResourceAddress::Dynamic(namespace, group, version, kind, name) => (
+                AddressScope::Namespaced(namespace),
+                Some(format!("{group}/{version}/{kind}").into()),
+                name,
+            ),
+            K8sResourceAddress::ClusterDynamic(group, version, kind, name) => {
+                (AddressScope::Cluster, Some(format!("{group}/{version}/{kind}").into()), name)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -46,17 +189,19 @@ pub struct K8sClusterAddress {
 
 impl ResourceAddress for K8sClusterAddress {
     fn from_path(path: &Path) -> anyhow::Result<Self> {
-        let path_components: Vec<&str> = path
-            .components()
-            .into_iter()
-            .map(|s| s.as_os_str().to_str().unwrap_or_default())
-            .collect();
+        let Some(path_components): Option<Vec<&str>> = path.components().into_iter().map(|s| s.as_os_str().to_str()).collect()
+        else {
+            return Err(invalid_addr_path(path));
+        };
 
         fn val(s: &str) -> bool {
-            s.ends_with(".yaml") || s.ends_with(".yml")
+            s.ends_with(".yaml") || s.ends_with(".yml") || s.ends_with(".json")
         }
         fn strip(s: &str) -> &str {
-            s.strip_suffix(".yaml").unwrap_or(s)
+            s.strip_suffix(".yaml")
+                .or_else(|| s.strip_suffix(".yml"))
+                .or_else(|| s.strip_suffix(".json"))
+                .unwrap_or(s)
         }
 
         match &path_components[..] {
@@ -64,6 +209,7 @@ impl ResourceAddress for K8sClusterAddress {
                 let res_addr = match &rest[..] {
                     ["ns", namespace, "ns.yaml"] => K8sResourceAddress::Namespace(namespace.to_string()),
                     ["ns", namespace, "ns.yml"] => K8sResourceAddress::Namespace(namespace.to_string()),
+                    ["ns", namespace, "ns.json"] => K8sResourceAddress::Namespace(namespace.to_string()),
                     ["ns", namespace, "pod", pod_name] if val(pod_name) => {
                         K8sResourceAddress::Pod(namespace.to_string(), strip(pod_name).to_string())
                     }
@@ -73,12 +219,48 @@ impl ResourceAddress for K8sClusterAddress {
                     ["ns", namespace, "deployment", deployment_name] if val(deployment_name) => {
                         K8sResourceAddress::Deployment(namespace.to_string(), strip(deployment_name).to_string())
                     }
+                    ["ns", namespace, "statefulset", statefulset_name] if val(statefulset_name) => {
+                        K8sResourceAddress::StatefulSet(namespace.to_string(), strip(statefulset_name).to_string())
+                    }
+                    ["ns", namespace, "daemonset", daemonset_name] if val(daemonset_name) => {
+                        K8sResourceAddress::DaemonSet(namespace.to_string(), strip(daemonset_name).to_string())
+                    }
+                    ["ns", namespace, "replicaset", replicaset_name] if val(replicaset_name) => {
+                        K8sResourceAddress::ReplicaSet(namespace.to_string(), strip(replicaset_name).to_string())
+                    }
+                    ["ns", namespace, "job", job_name] if val(job_name) => {
+                        K8sResourceAddress::Job(namespace.to_string(), strip(job_name).to_string())
+                    }
+                    ["ns", namespace, "cronjob", cronjob_name] if val(cronjob_name) => {
+                        K8sResourceAddress::CronJob(namespace.to_string(), strip(cronjob_name).to_string())
+                    }
                     ["ns", namespace, "configmap", configmap_name] if val(configmap_name) => {
                         K8sResourceAddress::ConfigMap(namespace.to_string(), strip(configmap_name).to_string())
                     }
-                    // ["ns", namespace, "secret", secret_name] if val(secret_name) => {
-                    //     K8sResourceAddress::Secret(namespace.to_string(), strip(secret_name).to_string())
-                    // }
+                    ["ns", namespace, "ingress", ingress_name] if val(ingress_name) => {
+                        K8sResourceAddress::Ingress(namespace.to_string(), strip(ingress_name).to_string())
+                    }
+                    ["ns", namespace, "networkpolicy", policy_name] if val(policy_name) => {
+                        K8sResourceAddress::NetworkPolicy(namespace.to_string(), strip(policy_name).to_string())
+                    }
+                    ["ns", namespace, "resourcequota", quota_name] if val(quota_name) => {
+                        K8sResourceAddress::ResourceQuota(namespace.to_string(), strip(quota_name).to_string())
+                    }
+                    ["ns", namespace, "limitrange", limitrange_name] if val(limitrange_name) => {
+                        K8sResourceAddress::LimitRange(namespace.to_string(), strip(limitrange_name).to_string())
+                    }
+                    ["ns", namespace, "serviceaccount", sa_name] if val(sa_name) => {
+                        K8sResourceAddress::ServiceAccount(namespace.to_string(), strip(sa_name).to_string())
+                    }
+                    ["ns", namespace, "horizontalpodautoscaler", hpa_name] if val(hpa_name) => {
+                        K8sResourceAddress::HorizontalPodAutoscaler(namespace.to_string(), strip(hpa_name).to_string())
+                    }
+                    ["ns", namespace, "poddisruptionbudget", pdb_name] if val(pdb_name) => {
+                        K8sResourceAddress::PodDisruptionBudget(namespace.to_string(), strip(pdb_name).to_string())
+                    }
+                    ["ns", namespace, "secret", secret_name] if val(secret_name) => {
+                        K8sResourceAddress::Secret(namespace.to_string(), strip(secret_name).to_string())
+                    }
                     ["ns", namespace, "persistentvolumeclaim", pvc_name] if val(pvc_name) => {
                         K8sResourceAddress::PersistentVolumeClaim(namespace.to_string(), strip(pvc_name).to_string())
                     }
@@ -97,6 +279,51 @@ impl ResourceAddress for K8sClusterAddress {
                     ["clusterrolebinding", role_name] if val(role_name) => {
                         K8sResourceAddress::ClusterRoleBinding(strip(role_name).to_string())
                     }
+                    ["ingressclass", class_name] if val(class_name) => {
+                        K8sResourceAddress::IngressClass(strip(class_name).to_string())
+                    }
+                    ["storageclass", class_name] if val(class_name) => {
+                        K8sResourceAddress::StorageClass(strip(class_name).to_string())
+                    }
+                    ["csidriver", driver_name] if val(driver_name) => {
+                        K8sResourceAddress::CSIDriver(strip(driver_name).to_string())
+                    }
+                    ["validatingwebhookconfiguration", webhook_name] if val(webhook_name) => {
+                        K8sResourceAddress::ValidatingWebhookConfiguration(strip(webhook_name).to_string())
+                    }
+                    ["mutatingwebhookconfiguration", webhook_name] if val(webhook_name) => {
+                        K8sResourceAddress::MutatingWebhookConfiguration(strip(webhook_name).to_string())
+                    }
+                    ["validatingadmissionpolicy", policy_name] if val(policy_name) => {
+                        K8sResourceAddress::ValidatingAdmissionPolicy(strip(policy_name).to_string())
+                    }
+                    ["validatingadmissionpolicybinding", binding_name] if val(binding_name) => {
+                        K8sResourceAddress::ValidatingAdmissionPolicyBinding(strip(binding_name).to_string())
+                    }
+                    ["priorityclass", class_name] if val(class_name) => {
+                        K8sResourceAddress::PriorityClass(strip(class_name).to_string())
+                    }
+                    ["runtimeclass", class_name] if val(class_name) => {
+                        K8sResourceAddress::RuntimeClass(strip(class_name).to_string())
+                    }
+                    ["node", node_name] if val(node_name) => K8sResourceAddress::Node(strip(node_name).to_string()),
+                    ["bundle", bundle_name] if val(bundle_name) => K8sResourceAddress::Bundle(strip(bundle_name).to_string()),
+                    ["customresourcedefinition", crd_name] if val(crd_name) => {
+                        K8sResourceAddress::CustomResourceDefinition(strip(crd_name).to_string())
+                    }
+                    ["ns", namespace, group, version, kind, name] if val(name) => K8sResourceAddress::Dynamic(
+                        namespace.to_string(),
+                        group.to_string(),
+                        version.to_string(),
+                        kind.to_string(),
+                        strip(name).to_string(),
+                    ),
+                    [group, version, kind, name] if *group != "ns" && val(name) => K8sResourceAddress::ClusterDynamic(
+                        group.to_string(),
+                        version.to_string(),
+                        kind.to_string(),
+                        strip(name).to_string(),
+                    ),
                     _ => return Err(invalid_addr_path(path)),
                 };
 
@@ -111,48 +338,195 @@ impl ResourceAddress for K8sClusterAddress {
     }
 
     fn to_path_buf(&self) -> PathBuf {
+        let (_, _, name) = self.res_addr.path_parts();
+        self.dir_path().join(format!("{name}.yaml"))
+    }
+}
+
+impl K8sClusterAddress {
+    /// The directory this address's file is in, e.g. `k8s/<cluster>/ns/<namespace>/deployment`.
+    /// It doesn't depend on the name, so any address of a kind finds the directory of every file of that kind.
+    pub fn dir_path(&self) -> PathBuf {
         let cluster = &self.cluster;
-        match &self.res_addr {
-            K8sResourceAddress::Namespace(namespace) => PathBuf::from(format!("k8s/{cluster}/ns/{}/ns.yaml", namespace)),
-            K8sResourceAddress::Pod(namespace, pod) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/pod/{}.yaml", namespace, pod))
-            }
-            K8sResourceAddress::Service(namespace, service) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/service/{}.yaml", namespace, service))
-            }
-            K8sResourceAddress::Deployment(namespace, deployment) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/deployment/{}.yaml", namespace, deployment))
-            }
-            K8sResourceAddress::ConfigMap(namespace, configmap) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/configmap/{}.yaml", namespace, configmap))
-            }
-            // K8sResourceAddress::Secret(namespace, secret) => {
-            //     PathBuf::from(format!("k8s/{cluster}/ns/{}/secret/{}.yaml", namespace, secret))
-            // }
-            K8sResourceAddress::PersistentVolumeClaim(namespace, pvc) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/persistentvolumeclaim/{}.yaml", namespace, pvc))
+        let (scope, kind, _) = self.res_addr.path_parts();
+
+        let scope_dir = match scope {
+            AddressScope::Cluster => format!("k8s/{cluster}"),
+            AddressScope::Namespaced(namespace) => format!("k8s/{cluster}/ns/{namespace}"),
+        };
+
+        match kind {
+            Some(kind) => PathBuf::from(format!("{scope_dir}/{kind}")),
+            None => PathBuf::from(scope_dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn ns_name(f: fn(Namespace, Name) -> K8sResourceAddress) -> K8sResourceAddress {
+        f(String::from("team-a"), String::from("web"))
+    }
+
+    fn name(f: fn(Name) -> K8sResourceAddress) -> K8sResourceAddress {
+        f(String::from("standard"))
+    }
+
+    /// One address of every variant.
+    fn every_address() -> Vec<K8sResourceAddress> {
+        vec![
+            K8sResourceAddress::Namespace(String::from("team-a")),
+            ns_name(K8sResourceAddress::Pod),
+            ns_name(K8sResourceAddress::Service),
+            ns_name(K8sResourceAddress::Deployment),
+            ns_name(K8sResourceAddress::StatefulSet),
+            ns_name(K8sResourceAddress::DaemonSet),
+            ns_name(K8sResourceAddress::ReplicaSet),
+            ns_name(K8sResourceAddress::Job),
+            ns_name(K8sResourceAddress::CronJob),
+            ns_name(K8sResourceAddress::ConfigMap),
+            ns_name(K8sResourceAddress::Ingress),
+            ns_name(K8sResourceAddress::NetworkPolicy),
+            ns_name(K8sResourceAddress::ResourceQuota),
+            ns_name(K8sResourceAddress::LimitRange),
+            ns_name(K8sResourceAddress::ServiceAccount),
+            ns_name(K8sResourceAddress::HorizontalPodAutoscaler),
+            ns_name(K8sResourceAddress::PodDisruptionBudget),
+            ns_name(K8sResourceAddress::Secret),
+            ns_name(K8sResourceAddress::PersistentVolumeClaim),
+            ns_name(K8sResourceAddress::Role),
+            ns_name(K8sResourceAddress::RoleBinding),
+            name(K8sResourceAddress::PersistentVolume),
+            name(K8sResourceAddress::ClusterRole),
+            name(K8sResourceAddress::ClusterRoleBinding),
+            name(K8sResourceAddress::IngressClass),
+            name(K8sResourceAddress::StorageClass),
+            name(K8sResourceAddress::CSIDriver),
+            name(K8sResourceAddress::ValidatingWebhookConfiguration),
+            name(K8sResourceAddress::MutatingWebhookConfiguration),
+            name(K8sResourceAddress::ValidatingAdmissionPolicy),
+            name(K8sResourceAddress::ValidatingAdmissionPolicyBinding),
+            name(K8sResourceAddress::PriorityClass),
+            name(K8sResourceAddress::RuntimeClass),
+            name(K8sResourceAddress::Node),
+            name(K8sResourceAddress::CustomResourceDefinition),
+            name(K8sResourceAddress::Bundle),
+            K8sResourceAddress::Dynamic(
+                String::from("team-a"),
+                String::from("cert-manager.io"),
+                String::from("v1"),
+                String::from("Certificate"),
+                String::from("web-tls"),
+            ),
+            K8sResourceAddress::Dynamic(
+                String::from("team-a"),
+                String::from("core"),
+                String::from("v1"),
+                String::from("Endpoints"),
+                String::from("web"),
+            ),
+            K8sResourceAddress::ClusterDynamic(
+                String::from("cert-manager.io"),
+                String::from("v1"),
+                String::from("ClusterIssuer"),
+                String::from("letsencrypt"),
+            ),
+        ]
+    }
+
+    /// Defines `variant()` and `VARIANTS` from one list. The match is exhaustive, so a new variant won't compile
+    /// until it's listed here, and `round_trip_covers_every_variant` then fails until it's added to `every_address`.
+    macro_rules! variants {
+        ($($variant:ident),* $(,)?) => {
+            const VARIANTS: &[&str] = &[$(stringify!($variant)),*];
+
+            fn variant(addr: &K8sResourceAddress) -> &'static str {
+                match addr {
+                    $(K8sResourceAddress::$variant(..) => stringify!($variant),)*
+                }
             }
-            K8sResourceAddress::PersistentVolume(pv) => PathBuf::from(format!("k8s/{cluster}persistentvolume/{}.yaml", pv)),
-            K8sResourceAddress::Role(namespace, role) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/role/{}.yaml", namespace, role))
+        };
+    }
+
+    variants!(
+        Namespace,
+        Pod,
+        Service,
+        Deployment,
+        StatefulSet,
+        DaemonSet,
+        ReplicaSet,
+        Job,
+        CronJob,
+        ConfigMap,
+        Ingress,
+        NetworkPolicy,
+        ResourceQuota,
+        LimitRange,
+        ServiceAccount,
+        HorizontalPodAutoscaler,
+        PodDisruptionBudget,
+        Secret,
+        PersistentVolumeClaim,
+        Role,
+        RoleBinding,
+        PersistentVolume,
+        ClusterRole,
+        ClusterRoleBinding,
+        IngressClass,
+        StorageClass,
+        CSIDriver,
+        ValidatingWebhookConfiguration,
+        MutatingWebhookConfiguration,
+        ValidatingAdmissionPolicy,
+        ValidatingAdmissionPolicyBinding,
+        PriorityClass,
+        RuntimeClass,
+        Node,
+        CustomResourceDefinition,
+        Bundle,
+        Dynamic,
+        ClusterDynamic,
+    );
+
+    #[test]
+    fn round_trip_covers_every_variant() {
+        let covered: HashSet<&str> = every_address().iter().map(variant).collect();
+        let missing: Vec<&&str> = VARIANTS.iter().filter(|name| !covered.contains(*name)).collect();
+        assert!(missing.is_empty(), "every_address has no example of {:?}", missing);
+    }
+
+    #[test]
+    fn round_trip() {
+        for res_addr in every_address() {
+            let addr = K8sClusterAddress {
+                cluster: String::from("prod"),
+                res_addr,
+            };
+            let path = addr.to_path_buf();
+            let parsed = K8sClusterAddress::from_path(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!(parsed, addr, "{}", path.display());
+            assert_eq!(path.parent(), Some(addr.dir_path().as_path()), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn round_trip_from_yml_and_json() {
+        for res_addr in every_address() {
+            let addr = K8sClusterAddress {
+                cluster: String::from("prod"),
+                res_addr,
+            };
+            let path = addr.to_path_buf();
+            for ext in ["yml", "json"] {
+                let path = path.with_extension(ext);
+                let parsed = K8sClusterAddress::from_path(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+                assert_eq!(parsed, addr, "{}", path.display());
             }
-            K8sResourceAddress::ClusterRole(name) => PathBuf::from(format!("k8s/{cluster}/clusterrole/{}.yaml", name)),
-            K8sResourceAddress::RoleBinding(namespace, name) => {
-                PathBuf::from(format!("k8s/{cluster}/ns/{}/rolebinding/{}.yaml", namespace, name))
-            }
-            K8sResourceAddress::ClusterRoleBinding(name) => {
-                PathBuf::from(format!("k8s/{cluster}/clusterrolebinding/{}.yaml", name))
-            }
-            // K8sResourceAddress::Binding(namespace, name) => {
-            //     PathBuf::from(format!("k8s/{cluster}/ns/{}/binding/{}.yaml", namespace, name))
-            // }
-            // K8sResourceAddress::Endpoints(_, _) => todo!(),
-            // K8sResourceAddress::LimitRange(_, _) => todo!(),
-            // K8sResourceAddress::Node(_, _) => todo!(),
-            // K8sResourceAddress::PodTemplate(_, _) => todo!(),
-            // K8sResourceAddress::ReplicationController(_, _) => todo!(),
-            // K8sResourceAddress::ResourceQuota(_, _) => todo!(),
-            // K8sResourceAddress::ServiceAccount(_, _) => todo!(),
         }
     }
 }
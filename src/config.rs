@@ -0,0 +1,117 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use autoschematic_core::util::RON;
+use serde::{Deserialize, Serialize};
+
+/// The connector config file, relative to the connector prefix.
+pub const CONFIG_PATH: &str = "k8s/config.ron";
+
+/// The cluster name used when no config file is present.
+pub const DEFAULT_CLUSTER: &str = "default";
+
+/// How many list queries run at once against a cluster during import, unless configured.
+pub const DEFAULT_LIST_CONCURRENCY: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct K8sConnectorConfig {
+    /// Clusters managed by this connector, keyed by the name used in
+    /// `k8s/<cluster>/...` addresses.
+    pub clusters: BTreeMap<String, K8sClusterConfig>,
+    /// Path to an age identity file used to encrypt Secret values at rest.
+    /// Relative paths are resolved against the connector prefix.
+    /// Secrets are not imported or managed unless this is set.
+    pub secret_key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct K8sClusterConfig {
+    /// Path to the kubeconfig file for this cluster.
+    /// Relative paths are resolved against the connector prefix, like `secret_key_file`.
+    /// If unset, the default kubeconfig (or in-cluster config) is used.
+    pub kubeconfig: Option<PathBuf>,
+    /// The kubeconfig context to use. If unset, the kubeconfig's current-context is used.
+    pub context: Option<String>,
+    /// The kubeconfig user to use, overriding the one from the context.
+    pub user: Option<String>,
+    /// If set, only these namespaces are listed on import.
+    pub namespaces: Option<Vec<String>>,
+    /// How many list queries run at once against this cluster during import.
+    /// Defaults to 16; lower it if the API server is rate-limiting imports.
+    pub list_concurrency: Option<usize>,
+    /// Which objects are left out on import.
+    pub import_filter: K8sImportFilter,
+}
+
+/// Rules for leaving objects out on import that Kubernetes, or a controller, manages on our behalf.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct K8sImportFilter {
+    /// Skip objects with a controller ownerReference, such as Pods owned by a ReplicaSet
+    /// or ReplicaSets owned by a Deployment: their controller recreates them from its own spec.
+    pub skip_controlled: bool,
+    /// Skip objects that the control plane generates, such as the `kube-root-ca.crt` ConfigMap
+    /// in every namespace and the `kubernetes` Service in `default`.
+    pub skip_generated: bool,
+    /// Namespaces that aren't imported unless they're named explicitly,
+    /// either in `namespaces` or by the path being imported.
+    pub skip_namespaces: Vec<String>,
+}
+
+impl Default for K8sImportFilter {
+    fn default() -> Self {
+        Self {
+            skip_controlled: true,
+            skip_generated: true,
+            skip_namespaces: vec![
+                String::from("kube-system"),
+                String::from("kube-public"),
+                String::from("kube-node-lease"),
+            ],
+        }
+    }
+}
+
+impl Default for K8sConnectorConfig {
+    fn default() -> Self {
+        Self {
+            clusters: BTreeMap::from([(DEFAULT_CLUSTER.to_string(), K8sClusterConfig::default())]),
+            secret_key_file: None,
+        }
+    }
+}
+
+impl K8sConnectorConfig {
+    /// Load the connector config from `<prefix>/k8s/config.ron`,
+    /// falling back to a single "default" cluster if the file doesn't exist.
+    pub fn try_load(prefix: &Path) -> anyhow::Result<Self> {
+        let config_path = prefix.join(CONFIG_PATH);
+
+        if !config_path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let config_str = std::fs::read_to_string(&config_path)?;
+        let config: Self = RON
+            .from_str(&config_str)
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+
+        Ok(config)
+    }
+
+    pub fn cluster(&self, cluster: &str) -> anyhow::Result<&K8sClusterConfig> {
+        self.clusters.get(cluster).with_context(|| {
+            format!(
+                "Unknown cluster \"{}\": not declared in {} (known clusters: {})",
+                cluster,
+                CONFIG_PATH,
+                self.clusters.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+}
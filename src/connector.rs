@@ -14,14 +14,33 @@ use autoschematic_core::{
     error::{AutoschematicError, AutoschematicErrorType},
     get_resource_response,
     tarpc_bridge::TarpcConnector,
-    util::{PrettyConfig, RON, ron_check_eq, ron_check_syntax},
+    util::{PrettyConfig, RON},
 };
-use k8s_openapi::api::{
-    apps::v1::Deployment,
-    core::v1::{ConfigMap, Namespace, NamespaceSpec, PersistentVolume, PersistentVolumeClaim, Pod, Secret, Service},
+use k8s_openapi::{
+    api::{
+        admissionregistration::v1::{
+            MutatingWebhookConfiguration, ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding,
+            ValidatingWebhookConfiguration,
+        },
+        apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+        autoscaling::v2::HorizontalPodAutoscaler,
+        batch::v1::{CronJob, Job},
+        core::v1::{
+            ConfigMap, LimitRange, Namespace, NamespaceSpec, Node, PersistentVolume, PersistentVolumeClaim, Pod, ResourceQuota,
+            Secret, Service, ServiceAccount,
+        },
+        networking::v1::{Ingress, IngressClass, NetworkPolicy},
+        node::v1::RuntimeClass,
+        policy::v1::PodDisruptionBudget,
+        rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+        scheduling::v1::PriorityClass,
+        storage::v1::{CSIDriver, StorageClass},
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
 };
 use kube::{
     Client, Config,
+    api::DynamicObject,
     config::{KubeConfigOptions, Kubeconfig},
 };
 use serde::{Deserialize, Serialize};
@@ -29,17 +48,24 @@ use tokio::sync::{Mutex, RwLock};
 
 use crate::{
     addr::{K8sClusterAddress, K8sResourceAddress},
-    util::strip_boring_fields,
+    config::{CONFIG_PATH, K8sClusterConfig, K8sConnectorConfig},
+    util::{SERDE, check_eq, check_syntax, strip_boring_fields},
 };
 
+mod bundle;
+mod dynamic;
 mod get;
 mod list;
+mod node;
 mod op_exec;
 mod plan;
+mod secret;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SerdeBackend {
     RON,
     YAML,
+    JSON,
 }
 
 impl SerdeBackend {
@@ -47,6 +73,7 @@ impl SerdeBackend {
         let res = match self {
             SerdeBackend::RON => RON.to_string_pretty(s, PrettyConfig::default())?,
             SerdeBackend::YAML => serde_yaml::to_string(s)?,
+            SerdeBackend::JSON => serde_json::to_string_pretty(s)? + "\n",
         };
         Ok(res)
     }
@@ -55,45 +82,78 @@ impl SerdeBackend {
         let res = match self {
             SerdeBackend::RON => RON.from_str(d)?,
             SerdeBackend::YAML => serde_yaml::from_str(d)?,
+            SerdeBackend::JSON => serde_json::from_str(d)?,
         };
         Ok(res)
     }
+
+    /// The backend for a resource file, by extension: `.json` files are JSON, and everything else is YAML.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => SerdeBackend::JSON,
+            _ => SerdeBackend::YAML,
+        }
+    }
+
+    /// Re-encode a document from this backend into another.
+    pub fn transcode(&self, to: SerdeBackend, s: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if *self == to {
+            return Ok(s.to_vec());
+        }
+        let v: serde_yaml::Value = self.from_str(str::from_utf8(s)?)?;
+        Ok(to.to_string(&v)?.into_bytes())
+    }
 }
 
 pub struct K8sConnector {
-    // outbox: ConnectorOutbox,
+    outbox: ConnectorOutbox,
     // pub name: String,
     prefix: PathBuf,
+    config: RwLock<K8sConnectorConfig>,
     client_cache: RwLock<HashMap<String, Arc<Client>>>,
+    /// The CustomResourceDefinitions installed in each cluster, as last listed.
+    crd_cache: RwLock<HashMap<String, Arc<Vec<CustomResourceDefinition>>>>,
+    /// The HorizontalPodAutoscalers in each (cluster, namespace), declared or live, as last looked up.
+    hpa_cache: RwLock<HashMap<(String, String), Arc<Vec<HorizontalPodAutoscaler>>>>,
 }
 
 impl K8sConnector {
-    pub fn clusters(&self) -> anyhow::Result<Vec<String>> {
-        Ok(vec![String::from("default")])
+    /// Send a progress message to the user. Nobody may be listening, so failures are ignored.
+    pub fn progress(&self, message: String) {
+        let _ = self.outbox.send(Some(message));
+    }
+
+    pub async fn clusters(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.config.read().await.clusters.keys().cloned().collect())
+    }
+
+    pub async fn cluster_config(&self, cluster: &str) -> anyhow::Result<K8sClusterConfig> {
+        Ok(self.config.read().await.cluster(cluster)?.clone())
     }
 
-    pub fn kubecfg(&self, cluster: &str) -> anyhow::Result<Option<String>> {
-        Ok(None)
+    pub async fn kubecfg(&self, cluster: &str) -> anyhow::Result<Option<PathBuf>> {
+        Ok(self.cluster_config(cluster).await?.kubeconfig.map(|path| self.prefix.join(path)))
     }
 
     pub async fn get_or_init_client(&self, cluster: &str) -> anyhow::Result<Arc<Client>> {
+        let cluster_config = self.cluster_config(cluster).await?;
+
         let mut cache = self.client_cache.write().await;
 
         if !cache.contains_key(cluster) {
-            let client = match self.kubecfg(cluster)? {
+            let options = KubeConfigOptions {
+                context: cluster_config.context.clone(),
+                cluster: None,
+                user: cluster_config.user.clone(),
+            };
+
+            let client = match &cluster_config.kubeconfig {
                 Some(kubecfg_path) => {
-                    let kubecfg = Kubeconfig::read_from(kubecfg_path)?;
-                    Client::try_from(
-                        Config::from_custom_kubeconfig(
-                            kubecfg,
-                            &KubeConfigOptions {
-                                context: None,
-                                cluster: Some(cluster.into()),
-                                user: None,
-                            },
-                        )
-                        .await?,
-                    )?
+                    let kubecfg = Kubeconfig::read_from(self.prefix.join(kubecfg_path))?;
+                    Client::try_from(Config::from_custom_kubeconfig(kubecfg, &options).await?)?
+                }
+                None if cluster_config.context.is_some() || cluster_config.user.is_some() => {
+                    Client::try_from(Config::from_kubeconfig(&options).await?)?
                 }
                 None => Client::try_default().await?,
             };
@@ -123,18 +183,27 @@ impl Connector for K8sConnector {
         }
 
         Ok(Arc::new(K8sConnector {
+            outbox,
             prefix: prefix.into(),
-            client_cache: RwLock::new(HashMap::new())
+            config: RwLock::new(K8sConnectorConfig::default()),
+            client_cache: RwLock::new(HashMap::new()),
+            crd_cache: RwLock::new(HashMap::new()),
+            hpa_cache: RwLock::new(HashMap::new()),
         }))
     }
 
     async fn init(&self) -> anyhow::Result<()> {
-        // *self.client.lock().await = Some(Client::try_default().await?);
+        *self.config.write().await = K8sConnectorConfig::try_load(&self.prefix)?;
+        self.client_cache.write().await.clear();
+        self.crd_cache.write().await.clear();
+        self.hpa_cache.write().await.clear();
         Ok(())
     }
 
     async fn filter(&self, addr: &Path) -> Result<FilterResponse, anyhow::Error> {
-        if let Ok(_) = K8sClusterAddress::from_path(addr) {
+        if addr == Path::new(CONFIG_PATH) {
+            Ok(FilterResponse::Config)
+        } else if let Ok(_) = K8sClusterAddress::from_path(addr) {
             Ok(FilterResponse::Resource)
         } else {
             Ok(FilterResponse::None)
@@ -146,7 +215,12 @@ impl Connector for K8sConnector {
     }
 
     async fn get(&self, addr: &Path) -> Result<Option<GetResourceResponse>, anyhow::Error> {
-        self.do_get(addr).await
+        // Resources are fetched as YAML, and written back in whichever format the file uses.
+        let Some(mut output) = self.do_get(addr).await? else {
+            return Ok(None);
+        };
+        output.resource_definition = SERDE.transcode(SerdeBackend::for_path(addr), &output.resource_definition)?;
+        Ok(Some(output))
     }
 
     async fn plan(
@@ -155,6 +229,9 @@ impl Connector for K8sConnector {
         current: Option<Vec<u8>>,
         desired: Option<Vec<u8>>,
     ) -> Result<Vec<PlanResponseElement>, anyhow::Error> {
+        let backend = SerdeBackend::for_path(addr);
+        let current = current.map(|s| backend.transcode(SERDE, &s)).transpose()?;
+        let desired = desired.map(|s| backend.transcode(SERDE, &s)).transpose()?;
         self.do_plan(addr, current, desired).await
     }
 
@@ -162,57 +239,117 @@ impl Connector for K8sConnector {
         self.do_op_exec(addr, op).await
     }
 
-    async fn eq(&self, addr: &Path, a: &[u8], b: &[u8]) -> Result<bool, anyhow::Error> {
-        let addr = K8sClusterAddress::from_path(addr)?;
+    async fn eq(&self, addr_path: &Path, a: &[u8], b: &[u8]) -> Result<bool, anyhow::Error> {
+        let addr = K8sClusterAddress::from_path(addr_path)?;
+
+        let backend = SerdeBackend::for_path(addr_path);
+        let a = &backend.transcode(SERDE, a)?;
+        let b = &backend.transcode(SERDE, b)?;
 
         match &addr.res_addr {
-            K8sResourceAddress::Namespace(_) => ron_check_eq::<Namespace>(a, b),
-            K8sResourceAddress::Pod(_, _) => ron_check_eq::<Pod>(a, b),
-            K8sResourceAddress::Service(_, _) => ron_check_eq::<Service>(a, b),
-            K8sResourceAddress::Deployment(_, _) => ron_check_eq::<Deployment>(a, b),
-            K8sResourceAddress::ConfigMap(_, _) => ron_check_eq::<ConfigMap>(a, b),
-            // K8sResourceAddress::Secret(_, _) => ron_check_eq::<Secret>(a, b),
-            K8sResourceAddress::PersistentVolumeClaim(_, _) => ron_check_eq::<PersistentVolumeClaim>(a, b),
-            K8sResourceAddress::PersistentVolume(_) => ron_check_eq::<PersistentVolume>(a, b),
-            K8sResourceAddress::Role(_, _) => ron_check_eq::<PersistentVolume>(a, b),
-            K8sResourceAddress::RoleBinding(_, _) => ron_check_eq::<PersistentVolume>(a, b),
-            K8sResourceAddress::ClusterRole(_) => ron_check_eq::<PersistentVolume>(a, b),
-            K8sResourceAddress::ClusterRoleBinding(_) => ron_check_eq::<PersistentVolume>(a, b),
+            K8sResourceAddress::Namespace(_) => check_eq::<Namespace>(a, b),
+            K8sResourceAddress::Pod(_, _) => check_eq::<Pod>(a, b),
+            K8sResourceAddress::Service(_, _) => check_eq::<Service>(a, b),
+            K8sResourceAddress::Deployment(namespace, name) => {
+                // Same as plan: replicas set by an HPA aren't drift.
+                if self.deployment_is_autoscaled(&addr.cluster, namespace, name).await {
+                    check_eq::<Deployment>(&plan::strip_replicas(a)?, &plan::strip_replicas(b)?)
+                } else {
+                    check_eq::<Deployment>(a, b)
+                }
+            }
+            K8sResourceAddress::StatefulSet(_, _) => check_eq::<StatefulSet>(a, b),
+            K8sResourceAddress::DaemonSet(_, _) => check_eq::<DaemonSet>(a, b),
+            K8sResourceAddress::ReplicaSet(_, _) => check_eq::<ReplicaSet>(a, b),
+            K8sResourceAddress::Job(_, _) => check_eq::<Job>(a, b),
+            K8sResourceAddress::CronJob(_, _) => check_eq::<CronJob>(a, b),
+            K8sResourceAddress::ConfigMap(_, _) => check_eq::<ConfigMap>(a, b),
+            K8sResourceAddress::Ingress(_, _) => check_eq::<Ingress>(a, b),
+            K8sResourceAddress::NetworkPolicy(_, _) => check_eq::<NetworkPolicy>(a, b),
+            K8sResourceAddress::ResourceQuota(_, _) => check_eq::<ResourceQuota>(a, b),
+            K8sResourceAddress::LimitRange(_, _) => check_eq::<LimitRange>(a, b),
+            K8sResourceAddress::ServiceAccount(_, _) => check_eq::<ServiceAccount>(a, b),
+            K8sResourceAddress::HorizontalPodAutoscaler(_, _) => check_eq::<HorizontalPodAutoscaler>(a, b),
+            K8sResourceAddress::PodDisruptionBudget(_, _) => check_eq::<PodDisruptionBudget>(a, b),
+            K8sResourceAddress::Secret(_, _) => self.secret_eq(a, b).await,
+            K8sResourceAddress::PersistentVolumeClaim(_, _) => check_eq::<PersistentVolumeClaim>(a, b),
+            K8sResourceAddress::PersistentVolume(_) => check_eq::<PersistentVolume>(a, b),
+            K8sResourceAddress::Role(_, _) => check_eq::<Role>(a, b),
+            K8sResourceAddress::RoleBinding(_, _) => check_eq::<RoleBinding>(a, b),
+            K8sResourceAddress::ClusterRole(_) => check_eq::<ClusterRole>(a, b),
+            K8sResourceAddress::ClusterRoleBinding(_) => check_eq::<ClusterRoleBinding>(a, b),
+            K8sResourceAddress::IngressClass(_) => check_eq::<IngressClass>(a, b),
+            K8sResourceAddress::StorageClass(_) => check_eq::<StorageClass>(a, b),
+            K8sResourceAddress::CSIDriver(_) => check_eq::<CSIDriver>(a, b),
+            K8sResourceAddress::ValidatingWebhookConfiguration(_) => check_eq::<ValidatingWebhookConfiguration>(a, b),
+            K8sResourceAddress::MutatingWebhookConfiguration(_) => check_eq::<MutatingWebhookConfiguration>(a, b),
+            K8sResourceAddress::ValidatingAdmissionPolicy(_) => check_eq::<ValidatingAdmissionPolicy>(a, b),
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(_) => check_eq::<ValidatingAdmissionPolicyBinding>(a, b),
+            K8sResourceAddress::PriorityClass(_) => check_eq::<PriorityClass>(a, b),
+            K8sResourceAddress::RuntimeClass(_) => check_eq::<RuntimeClass>(a, b),
+            K8sResourceAddress::Node(_) => check_eq::<Node>(a, b),
+            K8sResourceAddress::CustomResourceDefinition(_) => check_eq::<CustomResourceDefinition>(a, b),
+            K8sResourceAddress::Bundle(_) => self.bundle_eq(&addr.cluster, a, b).await,
+            K8sResourceAddress::Dynamic(..) => check_eq::<DynamicObject>(a, b),
+            K8sResourceAddress::ClusterDynamic(..) => check_eq::<DynamicObject>(a, b),
             // K8sResourceAddress::Binding(_, _) => todo!(),
             // K8sResourceAddress::Endpoints(_, _) => todo!(),
-            // K8sResourceAddress::LimitRange(_, _) => todo!(),
-            // K8sResourceAddress::Node(_, _) => todo!(),
             // K8sResourceAddress::PodTemplate(_, _) => todo!(),
             // K8sResourceAddress::ReplicationController(_, _) => todo!(),
-            // K8sResourceAddress::ResourceQuota(_, _) => todo!(),
-            // K8sResourceAddress::ServiceAccount(_, _) => todo!(),
         }
     }
 
-    async fn diag(&self, addr: &Path, a: &[u8]) -> Result<Option<DiagnosticResponse>, anyhow::Error> {
-        let addr = K8sClusterAddress::from_path(addr)?;
+    async fn diag(&self, addr_path: &Path, a: &[u8]) -> Result<Option<DiagnosticResponse>, anyhow::Error> {
+        let addr = K8sClusterAddress::from_path(addr_path)?;
+
+        // Files are checked in their own format, so that reported positions match what's on disk.
+        let backend = SerdeBackend::for_path(addr_path);
 
         match &addr.res_addr {
-            K8sResourceAddress::Namespace(_) => ron_check_syntax::<Namespace>(a),
-            K8sResourceAddress::Pod(_, _) => ron_check_syntax::<Pod>(a),
-            K8sResourceAddress::Service(_, _) => ron_check_syntax::<Service>(a),
-            K8sResourceAddress::Deployment(_, _) => ron_check_syntax::<Deployment>(a),
-            K8sResourceAddress::ConfigMap(_, _) => ron_check_syntax::<ConfigMap>(a),
-            // K8sResourceAddress::Secret(_, _) => ron_check_syntax::<Secret>(a),
-            K8sResourceAddress::PersistentVolumeClaim(_, _) => ron_check_syntax::<PersistentVolumeClaim>(a),
-            K8sResourceAddress::PersistentVolume(_) => ron_check_syntax::<PersistentVolume>(a),
-            K8sResourceAddress::Role(_, _) => ron_check_syntax::<PersistentVolume>(a),
-            K8sResourceAddress::RoleBinding(_, _) => ron_check_syntax::<PersistentVolume>(a),
-            K8sResourceAddress::ClusterRole(_) => ron_check_syntax::<PersistentVolume>(a),
-            K8sResourceAddress::ClusterRoleBinding(_) => ron_check_syntax::<PersistentVolume>(a),
+            K8sResourceAddress::Namespace(_) => check_syntax::<Namespace>(backend, a),
+            K8sResourceAddress::Pod(_, _) => check_syntax::<Pod>(backend, a),
+            K8sResourceAddress::Service(_, _) => check_syntax::<Service>(backend, a),
+            K8sResourceAddress::Deployment(_, _) => check_syntax::<Deployment>(backend, a),
+            K8sResourceAddress::StatefulSet(_, _) => check_syntax::<StatefulSet>(backend, a),
+            K8sResourceAddress::DaemonSet(_, _) => check_syntax::<DaemonSet>(backend, a),
+            K8sResourceAddress::ReplicaSet(_, _) => check_syntax::<ReplicaSet>(backend, a),
+            K8sResourceAddress::Job(_, _) => check_syntax::<Job>(backend, a),
+            K8sResourceAddress::CronJob(_, _) => check_syntax::<CronJob>(backend, a),
+            K8sResourceAddress::ConfigMap(_, _) => check_syntax::<ConfigMap>(backend, a),
+            K8sResourceAddress::Ingress(_, _) => check_syntax::<Ingress>(backend, a),
+            K8sResourceAddress::NetworkPolicy(_, _) => check_syntax::<NetworkPolicy>(backend, a),
+            K8sResourceAddress::ResourceQuota(_, _) => check_syntax::<ResourceQuota>(backend, a),
+            K8sResourceAddress::LimitRange(_, _) => check_syntax::<LimitRange>(backend, a),
+            K8sResourceAddress::ServiceAccount(_, _) => check_syntax::<ServiceAccount>(backend, a),
+            K8sResourceAddress::HorizontalPodAutoscaler(_, _) => check_syntax::<HorizontalPodAutoscaler>(backend, a),
+            K8sResourceAddress::PodDisruptionBudget(_, _) => check_syntax::<PodDisruptionBudget>(backend, a),
+            K8sResourceAddress::Secret(_, _) => secret::diag_secret(backend, a),
+            K8sResourceAddress::PersistentVolumeClaim(_, _) => check_syntax::<PersistentVolumeClaim>(backend, a),
+            K8sResourceAddress::PersistentVolume(_) => check_syntax::<PersistentVolume>(backend, a),
+            K8sResourceAddress::Role(_, _) => check_syntax::<Role>(backend, a),
+            K8sResourceAddress::RoleBinding(_, _) => check_syntax::<RoleBinding>(backend, a),
+            K8sResourceAddress::ClusterRole(_) => check_syntax::<ClusterRole>(backend, a),
+            K8sResourceAddress::ClusterRoleBinding(_) => check_syntax::<ClusterRoleBinding>(backend, a),
+            K8sResourceAddress::IngressClass(_) => check_syntax::<IngressClass>(backend, a),
+            K8sResourceAddress::StorageClass(_) => check_syntax::<StorageClass>(backend, a),
+            K8sResourceAddress::CSIDriver(_) => check_syntax::<CSIDriver>(backend, a),
+            K8sResourceAddress::ValidatingWebhookConfiguration(_) => check_syntax::<ValidatingWebhookConfiguration>(backend, a),
+            K8sResourceAddress::MutatingWebhookConfiguration(_) => check_syntax::<MutatingWebhookConfiguration>(backend, a),
+            K8sResourceAddress::ValidatingAdmissionPolicy(_) => check_syntax::<ValidatingAdmissionPolicy>(backend, a),
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(_) => {
+                check_syntax::<ValidatingAdmissionPolicyBinding>(backend, a)
+            }
+            K8sResourceAddress::PriorityClass(_) => check_syntax::<PriorityClass>(backend, a),
+            K8sResourceAddress::RuntimeClass(_) => check_syntax::<RuntimeClass>(backend, a),
+            K8sResourceAddress::Node(_) => check_syntax::<Node>(backend, a),
+            K8sResourceAddress::CustomResourceDefinition(_) => check_syntax::<CustomResourceDefinition>(backend, a),
+            K8sResourceAddress::Bundle(_) => self.bundle_diag(&addr.cluster, a).await,
+            K8sResourceAddress::Dynamic(..) => check_syntax::<DynamicObject>(backend, a),
+            K8sResourceAddress::ClusterDynamic(..) => check_syntax::<DynamicObject>(backend, a),
             // K8sResourceAddress::Binding(_, _) => todo!(),
             // K8sResourceAddress::Endpoints(_, _) => todo!(),
-            // K8sResourceAddress::LimitRange(_, _) => todo!(),
-            // K8sResourceAddress::Node(_, _) => todo!(),
             // K8sResourceAddress::PodTemplate(_, _) => todo!(),
             // K8sResourceAddress::ReplicationController(_, _) => todo!(),
-            // K8sResourceAddress::ResourceQuota(_, _) => todo!(),
-            // K8sResourceAddress::ServiceAccount(_, _) => todo!(),
         }
     }
 }
@@ -0,0 +1,345 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+use anyhow::bail;
+use autoschematic_core::{
+    connector::{Connector, ConnectorOp, GetResourceResponse, OpExecResponse, PlanResponseElement, ResourceAddress},
+    diag::DiagnosticResponse,
+};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::{
+    addr::{K8sClusterAddress, K8sResourceAddress},
+    op::{K8sBundleOp, K8sConnectorOp},
+    util::SERDE,
+};
+
+use super::{K8sConnector, SerdeBackend, dynamic::CORE_GROUP_PATH};
+
+/// Split a multi-document YAML file into its documents, skipping empty ones.
+fn split_documents(s: &[u8]) -> anyhow::Result<Vec<Value>> {
+    let s = std::str::from_utf8(s)?;
+
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(s) {
+        let v = Value::deserialize(document)?;
+        if !v.is_null() {
+            documents.push(v);
+        }
+    }
+    Ok(documents)
+}
+
+/// Resolve the address of one object in a bundle from its own apiVersion, kind and metadata.
+/// Kinds without a typed address fall back to a dynamic address, scoped by whether the object names a namespace.
+fn member_addr(cluster: &str, v: &Value) -> anyhow::Result<K8sClusterAddress> {
+    let (Some(api_version), Some(kind), Some(name)) =
+        (v["apiVersion"].as_str(), v["kind"].as_str(), v["metadata"]["name"].as_str())
+    else {
+        bail!("Every document in a bundle needs apiVersion, kind and metadata.name");
+    };
+
+    let explicit_namespace = v["metadata"]["namespace"].as_str();
+    let namespace = explicit_namespace.unwrap_or("default").to_string();
+    let (group, version) = api_version.rsplit_once('/').unwrap_or(("", api_version));
+    let name = name.to_string();
+
+    let res_addr = match (group, kind) {
+        ("", "Namespace") => K8sResourceAddress::Namespace(name),
+        ("", "Pod") => K8sResourceAddress::Pod(namespace, name),
+        ("", "Service") => K8sResourceAddress::Service(namespace, name),
+        ("", "ConfigMap") => K8sResourceAddress::ConfigMap(namespace, name),
+        ("", "ResourceQuota") => K8sResourceAddress::ResourceQuota(namespace, name),
+        ("", "LimitRange") => K8sResourceAddress::LimitRange(namespace, name),
+        ("", "ServiceAccount") => K8sResourceAddress::ServiceAccount(namespace, name),
+        ("", "Secret") => K8sResourceAddress::Secret(namespace, name),
+        ("", "PersistentVolumeClaim") => K8sResourceAddress::PersistentVolumeClaim(namespace, name),
+        ("", "PersistentVolume") => K8sResourceAddress::PersistentVolume(name),
+        ("", "Node") => K8sResourceAddress::Node(name),
+        ("apps", "Deployment") => K8sResourceAddress::Deployment(namespace, name),
+        ("apps", "StatefulSet") => K8sResourceAddress::StatefulSet(namespace, name),
+        ("apps", "DaemonSet") => K8sResourceAddress::DaemonSet(namespace, name),
+        ("apps", "ReplicaSet") => K8sResourceAddress::ReplicaSet(namespace, name),
+        ("batch", "Job") => K8sResourceAddress::Job(namespace, name),
+        ("batch", "CronJob") => K8sResourceAddress::CronJob(namespace, name),
+        ("autoscaling", "HorizontalPodAutoscaler") => K8sResourceAddress::HorizontalPodAutoscaler(namespace, name),
+        ("policy", "PodDisruptionBudget") => K8sResourceAddress::PodDisruptionBudget(namespace, name),
+        ("networking.k8s.io", "Ingress") => K8sResourceAddress::Ingress(namespace, name),
+        ("networking.k8s.io", "NetworkPolicy") => K8sResourceAddress::NetworkPolicy(namespace, name),
+        ("networking.k8s.io", "IngressClass") => K8sResourceAddress::IngressClass(name),
+        ("rbac.authorization.k8s.io", "Role") => K8sResourceAddress::Role(namespace, name),
+        ("rbac.authorization.k8s.io", "RoleBinding") => K8sResourceAddress::RoleBinding(namespace, name),
+        ("rbac.authorization.k8s.io", "ClusterRole") => K8sResourceAddress::ClusterRole(name),
+        ("rbac.authorization.k8s.io", "ClusterRoleBinding") => K8sResourceAddress::ClusterRoleBinding(name),
+        ("storage.k8s.io", "StorageClass") => K8sResourceAddress::StorageClass(name),
+        ("storage.k8s.io", "CSIDriver") => K8sResourceAddress::CSIDriver(name),
+        ("admissionregistration.k8s.io", "ValidatingWebhookConfiguration") => {
+            K8sResourceAddress::ValidatingWebhookConfiguration(name)
+        }
+        ("admissionregistration.k8s.io", "MutatingWebhookConfiguration") => {
+            K8sResourceAddress::MutatingWebhookConfiguration(name)
+        }
+        ("admissionregistration.k8s.io", "ValidatingAdmissionPolicy") => K8sResourceAddress::ValidatingAdmissionPolicy(name),
+        ("admissionregistration.k8s.io", "ValidatingAdmissionPolicyBinding") => {
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(name)
+        }
+        ("scheduling.k8s.io", "PriorityClass") => K8sResourceAddress::PriorityClass(name),
+        ("node.k8s.io", "RuntimeClass") => K8sResourceAddress::RuntimeClass(name),
+        ("apiextensions.k8s.io", "CustomResourceDefinition") => K8sResourceAddress::CustomResourceDefinition(name),
+        _ => {
+            let group = if group.is_empty() { CORE_GROUP_PATH } else { group };
+            match explicit_namespace {
+                Some(namespace) => K8sResourceAddress::Dynamic(
+                    namespace.to_string(),
+                    group.to_string(),
+                    version.to_string(),
+                    kind.to_lowercase(),
+                    name,
+                ),
+                None => K8sResourceAddress::ClusterDynamic(group.to_string(), version.to_string(), kind.to_lowercase(), name),
+            }
+        }
+    };
+
+    Ok(K8sClusterAddress {
+        cluster: cluster.to_string(),
+        res_addr,
+    })
+}
+
+/// The objects in a bundle file, each paired with the address it would have as a standalone file.
+fn bundle_members(cluster: &str, s: &[u8]) -> anyhow::Result<Vec<(K8sClusterAddress, Value)>> {
+    let mut members: Vec<(K8sClusterAddress, Value)> = Vec::new();
+    for document in split_documents(s)? {
+        let addr = member_addr(cluster, &document)?;
+        if members.iter().any(|(a, _)| *a == addr) {
+            bail!("Bundle declares {} more than once", addr.to_path_buf().display());
+        }
+        members.push((addr, document));
+    }
+    Ok(members)
+}
+
+fn to_bytes(v: &Value) -> anyhow::Result<Vec<u8>> {
+    Ok(SERDE.to_string(v)?.into_bytes())
+}
+
+/// Bundle members are handled by the same do_plan/do_get/do_op_exec that dispatched to the bundle,
+/// so those recursive calls are boxed behind a concrete future type.
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = anyhow::Result<T>> + Send + 'a>>;
+
+impl K8sConnector {
+    fn plan_member<'a>(
+        &'a self,
+        path: &'a Path,
+        current: Option<Vec<u8>>,
+        desired: Option<Vec<u8>>,
+    ) -> BoxFuture<'a, Vec<PlanResponseElement>> {
+        Box::pin(self.do_plan(path, current, desired))
+    }
+
+    fn get_member(&self, path: PathBuf) -> BoxFuture<'_, Option<GetResourceResponse>> {
+        Box::pin(async move { self.do_get(&path).await })
+    }
+
+    fn op_exec_member<'a>(&'a self, path: &'a Path, op: String) -> BoxFuture<'a, OpExecResponse> {
+        Box::pin(async move { self.do_op_exec(path, &op).await })
+    }
+
+    /// Plan a bundle by planning each of its objects as if it were a standalone file.
+    /// Creates and modifications follow document order, so a Namespace or CRD listed first is applied first;
+    /// objects dropped from the bundle are deleted afterwards, in reverse order.
+    pub async fn plan_bundle(
+        &self,
+        cluster: &str,
+        name: &str,
+        current: &Option<Vec<u8>>,
+        desired: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Vec<PlanResponseElement>> {
+        let current = match current {
+            Some(current) => bundle_members(cluster, current)?,
+            None => Vec::new(),
+        };
+        let desired = match desired {
+            Some(desired) => bundle_members(cluster, desired)?,
+            None => Vec::new(),
+        };
+
+        let mut res = Vec::new();
+
+        for (addr, desired_v) in &desired {
+            let current_v = current.iter().find(|(a, _)| a == addr).map(|(_, v)| v);
+            let member_path = addr.to_path_buf();
+            let current_bytes = current_v.map(to_bytes).transpose()?;
+            let elements = self
+                .plan_member(&member_path, current_bytes, Some(to_bytes(desired_v)?))
+                .await?;
+            res.extend(wrap_member_ops(name, &member_path, elements)?);
+        }
+
+        for (addr, current_v) in current.iter().rev() {
+            if desired.iter().any(|(a, _)| a == addr) {
+                continue;
+            }
+            let member_path = addr.to_path_buf();
+            let elements = self.plan_member(&member_path, Some(to_bytes(current_v)?), None).await?;
+            res.extend(wrap_member_ops(name, &member_path, elements)?);
+        }
+
+        Ok(res)
+    }
+
+    pub async fn op_exec_bundle(&self, op: &str) -> anyhow::Result<OpExecResponse> {
+        let K8sBundleOp { member, op } = K8sBundleOp::from_str(op)?;
+        self.op_exec_member(&member, op.to_string()?).await
+    }
+
+    /// Reassemble a bundle from live state: every object the bundle file declares, in the same order.
+    /// Objects that don't exist in the cluster are left out.
+    pub async fn get_bundle(&self, cluster: &str, bundle_path: &Path) -> anyhow::Result<Option<GetResourceResponse>> {
+        let Ok(bundle_file) = std::fs::read(self.prefix.join(bundle_path)) else {
+            return Ok(None);
+        };
+
+        let mut documents = Vec::new();
+        for (addr, _) in bundle_members(cluster, &bundle_file)? {
+            let Some(member) = self.get_member(addr.to_path_buf()).await? else {
+                continue;
+            };
+            documents.push(String::from_utf8(member.resource_definition)?);
+        }
+
+        if documents.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(GetResourceResponse {
+            resource_definition: documents.join("---\n").into_bytes(),
+            outputs: None,
+        }))
+    }
+
+    /// Two bundles are equal if they declare the same objects in the same order, and each pair is equal.
+    pub async fn bundle_eq(&self, cluster: &str, a: &[u8], b: &[u8]) -> anyhow::Result<bool> {
+        let a = bundle_members(cluster, a)?;
+        let b = bundle_members(cluster, b)?;
+
+        if a.len() != b.len() {
+            return Ok(false);
+        }
+
+        for ((addr_a, v_a), (addr_b, v_b)) in a.iter().zip(&b) {
+            if addr_a != addr_b {
+                return Ok(false);
+            }
+            if !self.eq(&addr_a.to_path_buf(), &to_bytes(v_a)?, &to_bytes(v_b)?).await? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Check each object in a bundle as if it were a standalone file, reporting the first problem found.
+    pub async fn bundle_diag(&self, cluster: &str, a: &[u8]) -> anyhow::Result<Option<DiagnosticResponse>> {
+        for (addr, v) in bundle_members(cluster, a)? {
+            if let Some(diag) = self.diag(&addr.to_path_buf(), &to_bytes(&v)?).await? {
+                return Ok(Some(diag));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The paths of every object declared in the repo's bundle files for a cluster,
+    /// so that import doesn't also write them out as standalone files.
+    pub fn bundled_paths(&self, cluster: &str) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for bundle_file in self.bundle_files(cluster) {
+            let Ok(members) = bundle_members(cluster, &bundle_file) else {
+                continue;
+            };
+            paths.extend(members.into_iter().map(|(addr, _)| addr.to_path_buf()));
+        }
+        paths
+    }
+
+    /// The contents of every bundle file in the repo for a cluster.
+    pub fn bundle_files(&self, cluster: &str) -> Vec<Vec<u8>> {
+        let bundle_dir = K8sClusterAddress {
+            cluster: cluster.to_string(),
+            res_addr: K8sResourceAddress::Bundle(String::new()),
+        }
+        .dir_path();
+        let Ok(entries) = std::fs::read_dir(self.prefix.join(bundle_dir)) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .filter_map(|entry| std::fs::read(entry.path()).ok())
+            .collect()
+    }
+
+    /// The objects of `addr`'s kind declared in the repo, in the same namespace: every file in its directory,
+    /// and every bundle member that would be filed there. Files that can't be read or parsed are skipped.
+    pub fn declared_objects(&self, addr: &K8sClusterAddress) -> Vec<Value> {
+        let dir = addr.dir_path();
+        let mut objects = Vec::new();
+
+        if let Ok(entries) = std::fs::read_dir(self.prefix.join(&dir)) {
+            for entry in entries.flatten() {
+                let path = dir.join(entry.file_name());
+                if K8sClusterAddress::from_path(&path).is_err() {
+                    continue;
+                }
+                let Ok(file) = std::fs::read_to_string(self.prefix.join(&path)) else {
+                    continue;
+                };
+                if let Ok(v) = SerdeBackend::for_path(&path).from_str(&file) {
+                    objects.push(v);
+                }
+            }
+        }
+
+        for bundle_file in self.bundle_files(&addr.cluster) {
+            let Ok(members) = bundle_members(&addr.cluster, &bundle_file) else {
+                continue;
+            };
+            objects.extend(
+                members
+                    .into_iter()
+                    .filter(|(member, _)| member.dir_path() == dir)
+                    .map(|(_, v)| v),
+            );
+        }
+
+        objects
+    }
+}
+
+/// Wrap a member's planned ops so that op_exec routes them back to that member.
+fn wrap_member_ops(
+    bundle_name: &str,
+    member_path: &Path,
+    elements: Vec<PlanResponseElement>,
+) -> anyhow::Result<Vec<PlanResponseElement>> {
+    elements
+        .into_iter()
+        .map(|mut element| {
+            let op = K8sConnectorOp::from_str(&element.op_definition)?;
+            element.op_definition = K8sBundleOp {
+                member: member_path.to_path_buf(),
+                op,
+            }
+            .to_string()?;
+            element.friendly_message = element
+                .friendly_message
+                .map(|message| format!("[bundle {}] {}", bundle_name, message));
+            Ok(element)
+        })
+        .collect()
+}
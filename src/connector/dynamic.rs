@@ -0,0 +1,484 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::bail;
+use autoschematic_core::{
+    connector::{OpExecResponse, PlanResponseElement, ResourceAddress},
+    connector_op,
+};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::{
+    Api, Client,
+    api::{DeleteParams, DynamicObject, ListParams, PatchParams, PostParams},
+    core::{GroupVersion, GroupVersionKind},
+    discovery::{self, ApiCapabilities, ApiResource, Scope},
+    runtime::wait::{await_condition, conditions, delete::delete_and_finalize},
+};
+
+use crate::{
+    addr::{K8sClusterAddress, K8sResourceAddress},
+    config::K8sImportFilter,
+    op::K8sConnectorOp,
+    util::{SERDE, diff_yaml_values, from_str_option},
+};
+
+use super::{
+    K8sConnector,
+    list::{ListScope, ListTask, list_metadata_paged, list_paged},
+};
+
+/// The path component used for the core ("") API group in dynamic addresses.
+pub const CORE_GROUP_PATH: &str = "core";
+
+/// How long op_exec waits for a CRD declared in the repo to be established before creating its custom resources.
+const CRD_ESTABLISH_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn crd_is_established(crd: &CustomResourceDefinition) -> bool {
+    let Some(status) = &crd.status else { return false };
+    let Some(conditions) = &status.conditions else { return false };
+    conditions
+        .iter()
+        .any(|condition| condition.type_ == "Established" && condition.status == "True")
+}
+
+/// The version that instances of a CRD are listed and imported at:
+/// the storage version if it's served, otherwise the first served version.
+pub fn crd_import_version(crd: &CustomResourceDefinition) -> Option<&str> {
+    let versions = &crd.spec.versions;
+    versions
+        .iter()
+        .find(|v| v.storage && v.served)
+        .or_else(|| versions.iter().find(|v| v.served))
+        .map(|v| v.name.as_str())
+}
+
+/// The paths of the custom resources listed through `resources`. Namespaced instances outside `namespaces` are skipped.
+async fn list_custom_resource_paths(
+    cluster: String,
+    resources: Api<DynamicObject>,
+    ar: ApiResource,
+    namespaced: bool,
+    namespaces: &[String],
+    import_filter: &K8sImportFilter,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut res = Vec::new();
+    let kind = ar.kind.to_lowercase();
+
+    for resource in list_metadata_paged(&resources, &ListParams::default()).await? {
+        let Some(name) = &resource.metadata.name else { continue };
+        if !import_filter.includes(&ar.kind, &resource.metadata) {
+            continue;
+        }
+
+        let res_addr = match (namespaced, &resource.metadata.namespace) {
+            (true, Some(namespace)) if namespaces.contains(namespace) => K8sResourceAddress::Dynamic(
+                namespace.to_string(),
+                ar.group.clone(),
+                ar.version.clone(),
+                kind.clone(),
+                name.to_string(),
+            ),
+            (false, _) => {
+                K8sResourceAddress::ClusterDynamic(ar.group.clone(), ar.version.clone(), kind.clone(), name.to_string())
+            }
+            _ => continue,
+        };
+
+        res.push(
+            K8sClusterAddress {
+                cluster: cluster.clone(),
+                res_addr,
+            }
+            .to_path_buf(),
+        );
+    }
+
+    Ok(res)
+}
+
+/// Whether a CRD defines `kind` in `group` and serves it at `version`.
+fn crd_serves(crd: &CustomResourceDefinition, group: &str, version: &str, kind: &str) -> bool {
+    crd.spec.group == group
+        && crd.spec.names.kind.eq_ignore_ascii_case(kind)
+        && crd.spec.versions.iter().any(|v| v.name == version && v.served)
+}
+
+impl K8sConnector {
+    /// Resolve a group/version/kind triple from a dynamic address into an ApiResource via discovery.
+    /// Kinds are matched case-insensitively, so `deployment` and `Deployment` both resolve.
+    pub async fn resolve_api_resource(
+        &self,
+        client: &Client,
+        group: &str,
+        version: &str,
+        kind: &str,
+    ) -> anyhow::Result<(ApiResource, ApiCapabilities)> {
+        let api_group = if group == CORE_GROUP_PATH { "" } else { group };
+
+        let gv = GroupVersion::gv(api_group, version);
+        let Ok(discovered) = discovery::pinned_group(client, &gv).await else {
+            bail!("API group version {}/{} is not served by this cluster", group, version);
+        };
+
+        for (ar, caps) in discovered.versioned_resources(version) {
+            if ar.kind.eq_ignore_ascii_case(kind) {
+                return Ok((ar, caps));
+            }
+        }
+
+        bail!("Kind {} is not served by API group version {}/{}", kind, group, version)
+    }
+
+    /// Build a DynamicObject Api for the given scope, checking that the address scope
+    /// matches the scope reported by discovery.
+    pub async fn dynamic_api(
+        &self,
+        cluster: &str,
+        client: &Client,
+        namespace: Option<&str>,
+        group: &str,
+        version: &str,
+        kind: &str,
+    ) -> anyhow::Result<(Api<DynamicObject>, ApiResource)> {
+        let (ar, caps) = match self.resolve_api_resource(client, group, version, kind).await {
+            Ok(resolved) => resolved,
+            Err(e) => match self.explain_unresolved_kind(cluster, client, group, version, kind).await? {
+                Some(reason) => bail!("Cannot manage {} in {}/{}: {}", kind, group, version, reason),
+                None => return Err(e),
+            },
+        };
+
+        let api = match (namespace, &caps.scope) {
+            (Some(namespace), Scope::Namespaced) => Api::namespaced_with(client.clone(), namespace, &ar),
+            (None, Scope::Cluster) => Api::all_with(client.clone(), &ar),
+            (Some(_), Scope::Cluster) => bail!(
+                "{} is cluster-scoped, use k8s/<cluster>/{}/{}/{}/<name>.yaml",
+                ar.kind,
+                group,
+                version,
+                kind
+            ),
+            (None, Scope::Namespaced) => bail!(
+                "{} is namespaced, use k8s/<cluster>/ns/<namespace>/{}/{}/{}/<name>.yaml",
+                ar.kind,
+                group,
+                version,
+                kind
+            ),
+        };
+
+        Ok((api, ar))
+    }
+
+    /// When a group/version/kind isn't served by the cluster, check whether that's because
+    /// its CustomResourceDefinition is missing, not yet established, or doesn't serve that version.
+    /// Returns None if the CRDs don't explain it.
+    pub async fn explain_unresolved_kind(
+        &self,
+        cluster: &str,
+        client: &Client,
+        group: &str,
+        version: &str,
+        kind: &str,
+    ) -> anyhow::Result<Option<String>> {
+        if group == CORE_GROUP_PATH {
+            return Ok(None);
+        }
+
+        let crds = self.installed_crds(cluster, client).await?;
+        let crd = crds
+            .iter()
+            .find(|crd| crd.spec.group == group && crd.spec.names.kind.eq_ignore_ascii_case(kind));
+
+        let Some(crd) = crd else {
+            return Ok(Some(format!(
+                "no CustomResourceDefinition for kind {} in group {} is installed; \
+                 it must be declared in the repo or created before this resource can be created",
+                kind, group
+            )));
+        };
+
+        let crd_name = crd.metadata.name.clone().unwrap_or_default();
+
+        if !crd_is_established(crd) {
+            return Ok(Some(format!(
+                "CustomResourceDefinition {} is not yet established; \
+                 this resource can be created once the API server accepts it",
+                crd_name
+            )));
+        }
+
+        if !crd.spec.versions.iter().any(|v| v.name == version && v.served) {
+            return Ok(Some(format!(
+                "CustomResourceDefinition {} does not serve version {}",
+                crd_name, version
+            )));
+        }
+
+        Ok(None)
+    }
+
+    /// The CustomResourceDefinitions installed in a cluster. They're listed once and reused,
+    /// so that planning many custom resources doesn't list every CRD for each of them;
+    /// `list` refreshes them, and changing a CRD through op_exec forgets them.
+    pub async fn installed_crds(&self, cluster: &str, client: &Client) -> anyhow::Result<Arc<Vec<CustomResourceDefinition>>> {
+        if let Some(crds) = self.crd_cache.read().await.get(cluster) {
+            return Ok(crds.clone());
+        }
+        self.refresh_installed_crds(cluster, client).await
+    }
+
+    async fn refresh_installed_crds(
+        &self,
+        cluster: &str,
+        client: &Client,
+    ) -> anyhow::Result<Arc<Vec<CustomResourceDefinition>>> {
+        let crds: Api<CustomResourceDefinition> = Api::all(client.clone());
+        let crds = Arc::new(list_paged(&crds, &ListParams::default()).await?);
+        self.crd_cache.write().await.insert(cluster.to_string(), crds.clone());
+        Ok(crds)
+    }
+
+    pub async fn forget_installed_crds(&self, cluster: &str) {
+        self.crd_cache.write().await.remove(cluster);
+    }
+
+    /// The CustomResourceDefinition declared in the repo, in its own file or in a bundle,
+    /// that serves `kind` at `group`/`version`, if any.
+    pub fn declared_crd(&self, cluster: &str, group: &str, version: &str, kind: &str) -> Option<CustomResourceDefinition> {
+        let addr = K8sClusterAddress {
+            cluster: cluster.to_string(),
+            res_addr: K8sResourceAddress::CustomResourceDefinition(String::new()),
+        };
+        self.declared_objects(&addr)
+            .into_iter()
+            .filter_map(|v| serde_yaml::from_value::<CustomResourceDefinition>(v).ok())
+            .find(|crd| crd_serves(crd, group, version, kind))
+    }
+
+    /// Before creating a custom resource whose kind isn't served yet, wait for the CRD declared
+    /// for it to be established, since it may have been created earlier in the same apply.
+    /// Kinds that resolve, or that no declared CRD serves, are left for dynamic_api to resolve or explain.
+    pub async fn wait_for_declared_crd(
+        &self,
+        cluster: &str,
+        client: &Client,
+        group: &str,
+        version: &str,
+        kind: &str,
+    ) -> anyhow::Result<()> {
+        if group == CORE_GROUP_PATH || self.resolve_api_resource(client, group, version, kind).await.is_ok() {
+            return Ok(());
+        }
+        let Some(crd_name) = self
+            .declared_crd(cluster, group, version, kind)
+            .and_then(|crd| crd.metadata.name)
+        else {
+            return Ok(());
+        };
+
+        let crds: Api<CustomResourceDefinition> = Api::all(client.clone());
+        let established = await_condition(crds, &crd_name, conditions::is_crd_established());
+        match tokio::time::timeout(CRD_ESTABLISH_TIMEOUT, established).await {
+            Ok(res) => {
+                res?;
+            }
+            Err(_) => bail!(
+                "Timed out after {}s waiting for CustomResourceDefinition {} to be established",
+                CRD_ESTABLISH_TIMEOUT.as_secs(),
+                crd_name
+            ),
+        }
+
+        self.forget_installed_crds(cluster).await;
+        Ok(())
+    }
+
+    /// List every installed CustomResourceDefinition, and queue a list query for the instances
+    /// of each established CRD at its import version. Namespaced instances are only listed in `namespaces`.
+    /// When the cluster config restricts namespaces, the CRDs themselves and cluster-scoped instances are skipped,
+    /// and namespaced instances are listed one namespace at a time, since access may only be granted in those namespaces.
+    pub async fn list_custom_resources<'a>(
+        &self,
+        cluster: &str,
+        client: &Client,
+        scope: &ListScope,
+        namespaces: &'a [String],
+        import_filter: &'a K8sImportFilter,
+        tasks: &mut Vec<ListTask<'a>>,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut res = Vec::new();
+        let cluster_wide = self.cluster_config(cluster).await?.namespaces.is_none();
+
+        for crd in self.refresh_installed_crds(cluster, client).await?.iter() {
+            let Some(crd_name) = crd.metadata.name.clone() else { continue };
+            if cluster_wide && scope.includes_cluster_scoped() && scope.includes_kind("customresourcedefinition") {
+                res.push(
+                    K8sClusterAddress {
+                        cluster: cluster.to_string(),
+                        res_addr: K8sResourceAddress::CustomResourceDefinition(crd_name),
+                    }
+                    .to_path_buf(),
+                );
+            }
+
+            if !crd_is_established(crd) {
+                continue;
+            }
+
+            // Custom resources are filed under their group.
+            let namespaced = crd.spec.scope == "Namespaced";
+            let in_scope = match namespaced {
+                true => scope.includes_namespaced(),
+                false => cluster_wide && scope.includes_cluster_scoped(),
+            };
+            if !in_scope || !scope.includes_kind(&crd.spec.group) {
+                continue;
+            }
+
+            let Some(version) = crd_import_version(crd) else { continue };
+
+            let gvk = GroupVersionKind::gvk(&crd.spec.group, version, &crd.spec.names.kind);
+            let ar = ApiResource::from_gvk_with_plural(&gvk, &crd.spec.names.plural);
+            let kind = crd.spec.names.kind.clone();
+
+            if namespaced && !cluster_wide {
+                for namespace in namespaces {
+                    let resources: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &ar);
+                    let query = list_custom_resource_paths(
+                        cluster.to_string(),
+                        resources,
+                        ar.clone(),
+                        namespaced,
+                        namespaces,
+                        import_filter,
+                    );
+                    tasks.push(ListTask::namespaced(kind.clone(), namespace, query));
+                }
+            } else {
+                let resources: Api<DynamicObject> = Api::all_with(client.clone(), &ar);
+                let query =
+                    list_custom_resource_paths(cluster.to_string(), resources, ar, namespaced, namespaces, import_filter);
+                tasks.push(ListTask::new(kind, query));
+            }
+        }
+
+        Ok(res)
+    }
+
+    pub async fn plan_dynamic(
+        &self,
+        cluster: &str,
+        client: &Client,
+        res_addr: &K8sResourceAddress,
+        current: &Option<Vec<u8>>,
+        desired: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Option<PlanResponseElement>> {
+        let (group, version, kind, display_name) = match res_addr {
+            K8sResourceAddress::Dynamic(namespace, group, version, kind, name) => {
+                (group, version, kind, format!("{}/{}", namespace, name))
+            }
+            K8sResourceAddress::ClusterDynamic(group, version, kind, name) => (group, version, kind, name.clone()),
+            _ => bail!("plan_dynamic: not a dynamic address: {:?}", res_addr),
+        };
+        let kind_label = format!("{}/{}/{}", group, version, kind);
+
+        let current: Option<DynamicObject> = from_str_option(current)?;
+        let desired: Option<DynamicObject> = from_str_option(desired)?;
+
+        let op = match (current, desired) {
+            (None, Some(desired)) => {
+                // A create that can't be executed yet is only planned if the repo declares the CRD it needs,
+                // which op_exec waits for. Otherwise it would fail halfway through an apply.
+                let mut note = String::new();
+                if let Err(e) = self.resolve_api_resource(client, group, version, kind).await {
+                    let Some(reason) = self.explain_unresolved_kind(cluster, client, group, version, kind).await? else {
+                        return Err(e);
+                    };
+                    let Some(crd) = self.declared_crd(cluster, group, version, kind) else {
+                        bail!("Cannot create {} {} yet: {}", kind_label, display_name, reason);
+                    };
+                    note = format!(
+                        " (waits for CustomResourceDefinition {} to be established)",
+                        crd.metadata.name.unwrap_or_default()
+                    );
+                }
+
+                Some(connector_op!(
+                    K8sConnectorOp::Create(SERDE.to_string(&desired)?),
+                    format!("Create {} {}{}", kind_label, display_name, note)
+                ))
+            }
+
+            (Some(_), None) => Some(connector_op!(
+                K8sConnectorOp::Delete,
+                format!("Delete {} {}", kind_label, display_name)
+            )),
+
+            (Some(current), Some(desired)) => {
+                let diff = diff_yaml_values(&current, &desired)?;
+                Some(connector_op!(
+                    K8sConnectorOp::Patch(SERDE.to_string(&desired)?),
+                    format!("Modify {} {}:\n{}", kind_label, display_name, diff)
+                ))
+            }
+            _ => None,
+        };
+
+        Ok(op)
+    }
+
+    pub async fn op_exec_dynamic(
+        &self,
+        api: Api<DynamicObject>,
+        ar: &ApiResource,
+        name: &str,
+        op: K8sConnectorOp,
+    ) -> anyhow::Result<OpExecResponse> {
+        let patch_params = PatchParams {
+            field_manager: Some(String::from("autoschematic")),
+            ..Default::default()
+        };
+
+        let post_params = PostParams {
+            field_manager: Some(String::from("autoschematic")),
+            ..Default::default()
+        };
+
+        let output = match op {
+            K8sConnectorOp::Create(resource) => {
+                let resource: DynamicObject = SERDE.from_str(&resource)?;
+                api.create(&post_params, &resource).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Created {} {}", ar.kind, name)),
+                }
+            }
+            K8sConnectorOp::Patch(resource) => {
+                let resource: DynamicObject = SERDE.from_str(&resource)?;
+                api.patch(name, &patch_params, &kube::api::Patch::Apply(resource)).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Modified {} {}", ar.kind, name)),
+                }
+            }
+            K8sConnectorOp::Replace(resource) => {
+                let resource: DynamicObject = SERDE.from_str(&resource)?;
+                delete_and_finalize(api.clone(), name, &DeleteParams::background()).await?;
+                api.create(&post_params, &resource).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Replaced {} {}", ar.kind, name)),
+                }
+            }
+            K8sConnectorOp::Delete => {
+                api.delete(name, &DeleteParams::default()).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Deleted {} {}", ar.kind, name)),
+                }
+            }
+        };
+
+        Ok(output)
+    }
+}
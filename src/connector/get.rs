@@ -1,11 +1,28 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 use anyhow::bail;
 use autoschematic_core::connector::{GetResourceResponse, ResourceAddress};
-use k8s_openapi::api::{
-    apps::v1::Deployment,
-    core::v1::{ConfigMap, Namespace, PersistentVolume, PersistentVolumeClaim, Pod, Secret, Service},
-    rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+use k8s_openapi::{
+    api::{
+        admissionregistration::v1::{
+            MutatingWebhookConfiguration, ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding,
+            ValidatingWebhookConfiguration,
+        },
+        apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+        autoscaling::v2::HorizontalPodAutoscaler,
+        batch::v1::{CronJob, Job},
+        core::v1::{
+            ConfigMap, LimitRange, Namespace, PersistentVolume, PersistentVolumeClaim, Pod, ResourceQuota, Secret, Service,
+            ServiceAccount,
+        },
+        networking::v1::{Ingress, IngressClass, NetworkPolicy},
+        node::v1::RuntimeClass,
+        policy::v1::PodDisruptionBudget,
+        rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+        scheduling::v1::PriorityClass,
+        storage::v1::{CSIDriver, StorageClass},
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
 };
 use kube::{Api, client::ClientBuilder};
 
@@ -44,9 +61,34 @@ macro_rules! get {
     }};
 }
 
+/// The address the ingress controller assigned to an Ingress, if any.
+fn ingress_outputs(ingress: &Ingress) -> Option<HashMap<String, String>> {
+    let lb_ingress = ingress
+        .status
+        .as_ref()?
+        .load_balancer
+        .as_ref()?
+        .ingress
+        .as_ref()?
+        .first()?;
+
+    let mut outputs = HashMap::new();
+    if let Some(ip) = &lb_ingress.ip {
+        outputs.insert(String::from("load_balancer_ip"), ip.clone());
+    }
+    if let Some(hostname) = &lb_ingress.hostname {
+        outputs.insert(String::from("load_balancer_hostname"), hostname.clone());
+    }
+    if let Some(address) = lb_ingress.hostname.as_ref().or(lb_ingress.ip.as_ref()) {
+        outputs.insert(String::from("load_balancer_address"), address.clone());
+    }
+
+    Some(outputs)
+}
+
 impl K8sConnector {
-    pub async fn do_get(&self, addr: &Path) -> Result<Option<GetResourceResponse>, anyhow::Error> {
-        let addr = K8sClusterAddress::from_path(addr)?;
+    pub async fn do_get(&self, addr_path: &Path) -> Result<Option<GetResourceResponse>, anyhow::Error> {
+        let addr = K8sClusterAddress::from_path(addr_path)?;
 
         let client = (*self.get_or_init_client(&addr.cluster).await?).clone();
 
@@ -55,22 +97,66 @@ impl K8sConnector {
             K8sResourceAddress::Pod(namespace, name) => get!(client, Pod, namespace, name),
             K8sResourceAddress::Service(namespace, name) => get!(client, Service, namespace, name),
             K8sResourceAddress::Deployment(namespace, name) => get!(client, Deployment, namespace, name),
+            K8sResourceAddress::StatefulSet(namespace, name) => get!(client, StatefulSet, namespace, name),
+            K8sResourceAddress::DaemonSet(namespace, name) => get!(client, DaemonSet, namespace, name),
+            K8sResourceAddress::ReplicaSet(namespace, name) => get!(client, ReplicaSet, namespace, name),
+            K8sResourceAddress::Job(namespace, name) => get!(client, Job, namespace, name),
+            K8sResourceAddress::CronJob(namespace, name) => get!(client, CronJob, namespace, name),
             K8sResourceAddress::ConfigMap(namespace, name) => get!(client, ConfigMap, namespace, name),
-            // K8sResourceAddress::Secret(namespace, name) => get!(client, Secret, namespace, name),
+            K8sResourceAddress::Ingress(namespace, name) => {
+                let resources: Api<Ingress> = Api::namespaced(client, &namespace);
+                let Ok(resource) = resources.get(&name).await else { return Ok(None) };
+                let mut output = get_ser_resource_output(&resource)?;
+                if let Some(output) = &mut output {
+                    output.outputs = ingress_outputs(&resource);
+                }
+                Ok(output)
+            }
+            K8sResourceAddress::NetworkPolicy(namespace, name) => get!(client, NetworkPolicy, namespace, name),
+            K8sResourceAddress::ResourceQuota(namespace, name) => get!(client, ResourceQuota, namespace, name),
+            K8sResourceAddress::LimitRange(namespace, name) => get!(client, LimitRange, namespace, name),
+            K8sResourceAddress::ServiceAccount(namespace, name) => get!(client, ServiceAccount, namespace, name),
+            K8sResourceAddress::HorizontalPodAutoscaler(namespace, name) => {
+                get!(client, HorizontalPodAutoscaler, namespace, name)
+            }
+            K8sResourceAddress::PodDisruptionBudget(namespace, name) => get!(client, PodDisruptionBudget, namespace, name),
+            K8sResourceAddress::Secret(namespace, name) => self.get_secret(&client, &namespace, &name).await,
             K8sResourceAddress::PersistentVolumeClaim(namespace, name) => get!(client, PersistentVolumeClaim, namespace, name),
             K8sResourceAddress::Role(namespace, name) => get!(client, Role, namespace, name),
             K8sResourceAddress::RoleBinding(namespace, name) => get!(client, RoleBinding, namespace, name),
             K8sResourceAddress::PersistentVolume(name) => get!(client, PersistentVolume, name),
             K8sResourceAddress::ClusterRole(name) => get!(client, ClusterRole, name),
             K8sResourceAddress::ClusterRoleBinding(name) => get!(client, ClusterRoleBinding, name),
+            K8sResourceAddress::IngressClass(name) => get!(client, IngressClass, name),
+            K8sResourceAddress::StorageClass(name) => get!(client, StorageClass, name),
+            K8sResourceAddress::CSIDriver(name) => get!(client, CSIDriver, name),
+            K8sResourceAddress::ValidatingWebhookConfiguration(name) => get!(client, ValidatingWebhookConfiguration, name),
+            K8sResourceAddress::MutatingWebhookConfiguration(name) => get!(client, MutatingWebhookConfiguration, name),
+            K8sResourceAddress::ValidatingAdmissionPolicy(name) => get!(client, ValidatingAdmissionPolicy, name),
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(name) => get!(client, ValidatingAdmissionPolicyBinding, name),
+            K8sResourceAddress::PriorityClass(name) => get!(client, PriorityClass, name),
+            K8sResourceAddress::RuntimeClass(name) => get!(client, RuntimeClass, name),
+            K8sResourceAddress::Node(name) => self.get_node(&client, &name).await,
+            K8sResourceAddress::CustomResourceDefinition(name) => get!(client, CustomResourceDefinition, name),
+            K8sResourceAddress::Bundle(_) => self.get_bundle(&addr.cluster, addr_path).await,
+            K8sResourceAddress::Dynamic(namespace, group, version, kind, name) => {
+                let (resources, _) = self
+                    .dynamic_api(&addr.cluster, &client, Some(namespace.as_str()), &group, &version, &kind)
+                    .await?;
+                let Ok(resource) = resources.get(&name).await else { return Ok(None) };
+                get_ser_resource_output(&resource)
+            }
+            K8sResourceAddress::ClusterDynamic(group, version, kind, name) => {
+                let (resources, _) = self
+                    .dynamic_api(&addr.cluster, &client, None, &group, &version, &kind)
+                    .await?;
+                let Ok(resource) = resources.get(&name).await else { return Ok(None) };
+                get_ser_resource_output(&resource)
+            }
             // K8sResourceAddress::Binding(_, _) => todo!(),
             // K8sResourceAddress::Endpoints(_, _) => todo!(),
-            // K8sResourceAddress::LimitRange(_, _) => todo!(),
-            // K8sResourceAddress::Node(_, _) => todo!(),
             // K8sResourceAddress::PodTemplate(_, _) => todo!(),
             // K8sResourceAddress::ReplicationController(_, _) => todo!(),
-            // K8sResourceAddress::ResourceQuota(_, _) => todo!(),
-            // K8sResourceAddress::ServiceAccount(_, _) => todo!(),
         }
     }
 }
@@ -1,109 +1,494 @@
 use std::{
     borrow::Cow,
-    path::{Path, PathBuf},
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    path::{Component, Path, PathBuf},
 };
 
 use anyhow::bail;
 use autoschematic_core::connector::ResourceAddress;
-use k8s_openapi::api::{
-    apps::v1::Deployment,
-    core::v1::{
-        ConfigMap, Namespace, PersistentVolume, PersistentVolumeClaim, PersistentVolumeClaimCondition, Pod, Secret, Service,
+use futures::{FutureExt, StreamExt, future::BoxFuture, stream};
+use k8s_openapi::{
+    api::{
+        admissionregistration::v1::{
+            MutatingWebhookConfiguration, ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding,
+            ValidatingWebhookConfiguration,
+        },
+        apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+        autoscaling::v2::HorizontalPodAutoscaler,
+        batch::v1::{CronJob, Job},
+        core::v1::{
+            ConfigMap, LimitRange, Namespace, PersistentVolume, PersistentVolumeClaim, PersistentVolumeClaimCondition, Pod,
+            ResourceQuota, Secret, Service, ServiceAccount,
+        },
+        networking::v1::{Ingress, IngressClass, NetworkPolicy},
+        node::v1::RuntimeClass,
+        policy::v1::PodDisruptionBudget,
+        rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+        scheduling::v1::PriorityClass,
+        storage::v1::{CSIDriver, StorageClass},
     },
-    rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
 };
-use kube::{Api, api::ListParams, runtime::reflector::Lookup};
+use kube::{
+    Api, Client,
+    api::{ListParams, ObjectList, ObjectMeta, PartialObjectMeta},
+    runtime::reflector::Lookup,
+};
+use serde::de::DeserializeOwned;
 
 use crate::addr::K8sClusterAddress;
 use crate::addr::K8sResourceAddress;
+use crate::config::{DEFAULT_LIST_CONCURRENCY, K8sClusterConfig, K8sImportFilter};
+use crate::neat::{neatify_resource, service_account_is_customized};
 
 use super::K8sConnector;
 
-macro_rules! list {
-    ($cluster:expr, $client:expr, $res:expr, $type:ident, $namespace:expr) => {{
-        let resources: Api<$type> = Api::namespaced($client.clone(), &$namespace);
-        for resource in resources.list_metadata(&ListParams::default()).await? {
-            let Some(name) = resource.name() else { continue };
-            $res.push(
-                K8sClusterAddress {
-                    cluster: $cluster.clone(),
-                    res_addr: K8sResourceAddress::$type($namespace.to_string(), name.to_string()),
+/// How many objects are requested per page when listing.
+const LIST_PAGE_SIZE: u32 = 500;
+
+/// How many times a listing restarts from the first page after its continue token expires.
+const MAX_LIST_RESTARTS: usize = 3;
+
+/// List the metadata of every object matching `params`, a page at a time, so that large collections
+/// don't have to be served in one response. If the continue token expires partway through
+/// (410 Gone, because the collection was compacted), the listing starts over from the first page.
+pub async fn list_metadata_paged<K>(api: &Api<K>, params: &ListParams) -> anyhow::Result<Vec<PartialObjectMeta<K>>>
+where
+    K: Clone + DeserializeOwned + Debug,
+{
+    list_pages(params, |params| async move { api.list_metadata(&params).await }).await
+}
+
+/// List every object matching `params` in full, paged the same way as `list_metadata_paged`.
+/// Only for when the whole object is needed, since full objects (CRDs with their schemas, especially)
+/// make for much larger pages.
+pub async fn list_paged<K>(api: &Api<K>, params: &ListParams) -> anyhow::Result<Vec<K>>
+where
+    K: Clone + DeserializeOwned + Debug,
+{
+    list_pages(params, |params| async move { api.list(&params).await }).await
+}
+
+async fn list_pages<T, F, Fut>(params: &ListParams, list_page: F) -> anyhow::Result<Vec<T>>
+where
+    T: Clone,
+    F: Fn(ListParams) -> Fut,
+    Fut: Future<Output = kube::Result<ObjectList<T>>>,
+{
+    let mut restarts = 0;
+    'restart: loop {
+        let mut items = Vec::new();
+        let mut params = params.clone().limit(LIST_PAGE_SIZE);
+        loop {
+            match list_page(params.clone()).await {
+                Ok(page) => {
+                    items.extend(page.items);
+                    match page.metadata.continue_ {
+                        Some(token) if !token.is_empty() => params = params.continue_token(&token),
+                        _ => return Ok(items),
+                    }
                 }
-                .to_path_buf(),
-            );
+                Err(kube::Error::Api(e)) if e.code == 410 && restarts < MAX_LIST_RESTARTS => {
+                    restarts += 1;
+                    continue 'restart;
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
-    }};
+    }
+}
 
-    ($cluster:expr, $client:expr, $res:expr, $type:ident) => {{
-        let resources: Api<$type> = Api::all($client.clone());
-        for resource in resources.list_metadata(&ListParams::default()).await? {
-            let Some(name) = resource.name() else { continue };
-            $res.push(
-                K8sClusterAddress {
-                    cluster: $cluster.clone(),
-                    res_addr: K8sResourceAddress::$type(name.to_string()),
-                }
-                .to_path_buf(),
-            );
+/// A single list query, run concurrently with the others for its cluster.
+pub struct ListTask<'a> {
+    /// The kind the query lists, and the namespace it lists it in, if any, for reporting it when it fails.
+    kind: String,
+    namespace: Option<String>,
+    query: BoxFuture<'a, anyhow::Result<Vec<PathBuf>>>,
+}
+
+impl<'a> ListTask<'a> {
+    pub fn new(kind: String, query: impl Future<Output = anyhow::Result<Vec<PathBuf>>> + Send + 'a) -> Self {
+        Self {
+            kind,
+            namespace: None,
+            query: query.boxed(),
         }
-    }};
+    }
+
+    pub fn namespaced(
+        kind: String,
+        namespace: &str,
+        query: impl Future<Output = anyhow::Result<Vec<PathBuf>>> + Send + 'a,
+    ) -> Self {
+        Self {
+            kind,
+            namespace: Some(namespace.to_string()),
+            query: query.boxed(),
+        }
+    }
+}
+
+/// The kinds whose list queries were refused for lack of permissions, with the namespaces they were refused in.
+/// Collected across a cluster's listing and reported once, grouped by kind, rather than once per query.
+#[derive(Debug, Default)]
+struct ForbiddenKinds(BTreeMap<String, BTreeSet<String>>);
+
+impl ForbiddenKinds {
+    fn add(&mut self, kind: &str, namespace: Option<&str>) {
+        let namespaces = self.0.entry(kind.to_string()).or_default();
+        if let Some(namespace) = namespace {
+            namespaces.insert(namespace.to_string());
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn contains(&self, kind: &str) -> bool {
+        self.0.contains_key(kind)
+    }
+}
+
+impl std::fmt::Display for ForbiddenKinds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kinds: Vec<String> = self
+            .0
+            .iter()
+            .map(|(kind, namespaces)| match namespaces.len() {
+                0 => kind.clone(),
+                1 => format!("{} (1 namespace)", kind),
+                n => format!("{} ({} namespaces)", kind, n),
+            })
+            .collect();
+        write!(f, "{}", kinds.join(", "))
+    }
+}
+
+/// Whether an error is the API server refusing a request for lack of RBAC permissions.
+fn is_forbidden(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<kube::Error>(), Some(kube::Error::Api(status)) if status.code == 403)
+}
+
+macro_rules! list {
+    ($cluster:expr, $client:expr, $tasks:expr, $scope:expr, $filter:expr, $type:ident, $namespace:expr) => {{ list_filtered!($cluster, $client, $tasks, $scope, $filter, $type, $namespace, |_, _| true) }};
+
+    ($cluster:expr, $client:expr, $tasks:expr, $scope:expr, $filter:expr, $type:ident) => {{ list_filtered!($cluster, $client, $tasks, $scope, $filter, $type, |_, _| true) }};
 }
 macro_rules! list_filtered {
-    ($cluster:expr, $client:expr, $res:expr, $type:ident, $namespace:expr, $predicate:expr) => {{
-        let resources: Api<$type> = Api::namespaced($client.clone(), &$namespace);
-        for resource in resources.list_metadata(&ListParams::default()).await? {
-            let Some(name) = resource.name() else { continue };
-            if !$predicate(&name) {
-                continue;
-            }
-            $res.push(
-                K8sClusterAddress {
-                    cluster: $cluster.clone(),
-                    res_addr: K8sResourceAddress::$type($namespace.to_string(), name.to_string()),
+    ($cluster:expr, $client:expr, $tasks:expr, $scope:expr, $filter:expr, $type:ident, $namespace:expr, $predicate:expr) => {{
+        if $scope.includes_kind(&stringify!($type).to_lowercase()) {
+            let cluster = $cluster.clone();
+            let client = $client.clone();
+            let filter: &K8sImportFilter = &$filter;
+            let namespace = $namespace.to_string();
+            let query = async move {
+                let mut res = Vec::new();
+                let resources: Api<$type> = Api::namespaced(client, &namespace);
+                for resource in list_metadata_paged(&resources, &ListParams::default()).await? {
+                    let Some(name) = resource.name() else { continue };
+                    if !$predicate(&name, &resource.metadata) || !filter.includes(stringify!($type), &resource.metadata) {
+                        continue;
+                    }
+                    res.push(
+                        K8sClusterAddress {
+                            cluster: cluster.clone(),
+                            res_addr: K8sResourceAddress::$type(namespace.clone(), name.to_string()),
+                        }
+                        .to_path_buf(),
+                    );
                 }
-                .to_path_buf(),
-            );
+                Ok(res)
+            };
+            $tasks.push(ListTask::namespaced(String::from(stringify!($type)), $namespace, query));
         }
     }};
 
-    ($cluster:expr, $client:expr, $res:expr, $type:ident, $predicate:expr) => {{
-        let resources: Api<$type> = Api::all($client.clone());
-        for resource in resources.list_metadata(&ListParams::default()).await? {
-            let Some(name) = resource.name() else { continue };
-            if !$predicate(&name) {
-                continue;
-            }
-            $res.push(
-                K8sClusterAddress {
-                    cluster: $cluster.clone(),
-                    res_addr: K8sResourceAddress::$type(name.to_string()),
+    ($cluster:expr, $client:expr, $tasks:expr, $scope:expr, $filter:expr, $type:ident, $predicate:expr) => {{
+        if $scope.includes_kind(&stringify!($type).to_lowercase()) {
+            let cluster = $cluster.clone();
+            let client = $client.clone();
+            let filter: &K8sImportFilter = &$filter;
+            let query = async move {
+                let mut res = Vec::new();
+                let resources: Api<$type> = Api::all(client);
+                for resource in list_metadata_paged(&resources, &ListParams::default()).await? {
+                    let Some(name) = resource.name() else { continue };
+                    if !$predicate(&name, &resource.metadata) || !filter.includes(stringify!($type), &resource.metadata) {
+                        continue;
+                    }
+                    res.push(
+                        K8sClusterAddress {
+                            cluster: cluster.clone(),
+                            res_addr: K8sResourceAddress::$type(name.to_string()),
+                        }
+                        .to_path_buf(),
+                    );
                 }
-                .to_path_buf(),
-            );
+                Ok(res)
+            };
+            $tasks.push(ListTask::new(String::from(stringify!($type)), query));
         }
     }};
 }
 
+/// The part of the tree that a `list` call covers, parsed from its subpath.
+/// Cluster-scoped kinds live at `k8s/<cluster>/<kind>/...` and namespaced ones at `k8s/<cluster>/ns/<namespace>/<kind>/...`,
+/// so e.g. `k8s/prod/ns/payments/deployment` covers only the Deployments in one namespace of one cluster.
+#[derive(Debug, Default)]
+pub struct ListScope {
+    cluster: Option<String>,
+    /// Only namespaced kinds, i.e. the subpath is under `k8s/<cluster>/ns`.
+    namespaced_only: bool,
+    /// Only cluster-scoped kinds, i.e. the subpath is under `k8s/<cluster>/<kind>`.
+    cluster_scoped_only: bool,
+    namespace: Option<String>,
+    /// The kind directory, e.g. `deployment`, or the group of a custom resource.
+    kind: Option<String>,
+}
+
+impl ListScope {
+    /// Parse a subpath, or return None if nothing this connector lists can be under it.
+    pub fn from_subpath(subpath: &Path) -> Option<Self> {
+        let parts: Vec<&str> = subpath
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        let scope = match parts.as_slice() {
+            [] | ["k8s"] => Self::default(),
+            ["k8s", cluster] => Self {
+                cluster: Some(cluster.to_string()),
+                ..Default::default()
+            },
+            ["k8s", cluster, "ns"] => Self {
+                cluster: Some(cluster.to_string()),
+                namespaced_only: true,
+                ..Default::default()
+            },
+            ["k8s", cluster, "ns", namespace] => Self {
+                cluster: Some(cluster.to_string()),
+                namespaced_only: true,
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            },
+            ["k8s", cluster, "ns", namespace, kind, ..] => Self {
+                cluster: Some(cluster.to_string()),
+                namespaced_only: true,
+                namespace: Some(namespace.to_string()),
+                kind: Some(kind.to_string()),
+                ..Default::default()
+            },
+            ["k8s", cluster, kind, ..] => Self {
+                cluster: Some(cluster.to_string()),
+                cluster_scoped_only: true,
+                kind: Some(kind.to_string()),
+                ..Default::default()
+            },
+            _ => return None,
+        };
+        Some(scope)
+    }
+
+    pub fn includes_cluster(&self, cluster: &str) -> bool {
+        self.cluster.as_deref().is_none_or(|c| c == cluster)
+    }
+
+    pub fn includes_cluster_scoped(&self) -> bool {
+        !self.namespaced_only
+    }
+
+    pub fn includes_namespaced(&self) -> bool {
+        !self.cluster_scoped_only
+    }
+
+    pub fn includes_kind(&self, kind: &str) -> bool {
+        self.kind.as_deref().is_none_or(|k| k == kind)
+    }
+
+    /// Whether CRDs need to be listed for this scope: when it names no kind, CRDs themselves,
+    /// or what looks like a custom resource's API group. A built-in kind only queries its own API.
+    pub fn includes_custom_resources(&self) -> bool {
+        self.kind
+            .as_deref()
+            .is_none_or(|k| k == "customresourcedefinition" || k.contains('.'))
+    }
+}
+
+/// Objects that the control plane creates by itself, as (kind, namespace, name), where None matches any namespace.
+const GENERATED_OBJECTS: &[(&str, Option<&str>, &str)] = &[
+    // Published into every namespace by the root CA cert publisher.
+    ("ConfigMap", None, "kube-root-ca.crt"),
+    // The API server's own Service.
+    ("Service", Some("default"), "kubernetes"),
+];
+
+impl K8sImportFilter {
+    /// Whether an object of the given kind should be imported.
+    pub fn includes(&self, kind: &str, meta: &ObjectMeta) -> bool {
+        if self.skip_controlled && is_controlled(meta) {
+            return false;
+        }
+
+        if self.skip_generated {
+            let generated = GENERATED_OBJECTS.iter().any(|(generated_kind, namespace, name)| {
+                *generated_kind == kind
+                    && meta.name.as_deref() == Some(*name)
+                    && namespace.is_none_or(|ns| meta.namespace.as_deref() == Some(ns))
+            });
+            if generated {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether an object is managed by a controller, e.g. a ReplicaSet owned by a Deployment or a Pod owned by a Job.
+fn is_controlled(meta: &ObjectMeta) -> bool {
+    meta.owner_references
+        .iter()
+        .flatten()
+        .any(|owner| owner.controller == Some(true))
+}
+
 impl K8sConnector {
+    async fn default_service_account_is_customized(&self, client: &Client, namespace: &str) -> anyhow::Result<bool> {
+        let service_accounts: Api<ServiceAccount> = Api::namespaced(client.clone(), namespace);
+        let Some(service_account) = service_accounts.get_opt("default").await? else {
+            return Ok(false);
+        };
+
+        let mut v = serde_yaml::to_value(&service_account)?;
+        neatify_resource(&mut v);
+        Ok(service_account_is_customized(&v))
+    }
+
     pub async fn do_list(&self, subpath: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
         let mut res = Vec::new();
 
-        for cluster in self.clusters()? {
+        let Some(scope) = ListScope::from_subpath(subpath) else {
+            return Ok(res);
+        };
+
+        for cluster in self.clusters().await? {
+            if !scope.includes_cluster(&cluster) {
+                continue;
+            }
+
+            let cluster_config = self.cluster_config(&cluster).await?;
             let client = (*self.get_or_init_client(&cluster).await?).clone();
 
-            list_filtered!(cluster, client, res, ClusterRole, |name: &Cow<str>| !name
-                .starts_with("system:"));
+            // Queries refused for lack of permissions. These are skipped rather than failing the whole import,
+            // so that least-privilege credentials can still import what they can see.
+            let mut forbidden = ForbiddenKinds::default();
+            let import_filter = &cluster_config.import_filter;
 
-            list_filtered!(cluster, client, res, ClusterRoleBinding, |name: &Cow<str>| !name
-                .starts_with("system:"));
+            let namespace_names: Vec<String> = match (&scope.namespace, &cluster_config.namespaces) {
+                _ if !scope.includes_namespaced() => Vec::new(),
+                (Some(namespace), Some(namespaces)) if !namespaces.contains(namespace) => Vec::new(),
+                (Some(namespace), Some(_)) => vec![namespace.clone()],
+                (Some(namespace), None) => {
+                    let nss: Api<Namespace> = Api::all(client.clone());
+                    match nss.get_metadata_opt(namespace).await {
+                        Ok(Some(_)) => vec![namespace.clone()],
+                        Ok(None) => Vec::new(),
+                        // We may be allowed into the namespace without being allowed to read the Namespace itself.
+                        Err(kube::Error::Api(status)) if status.code == 403 => vec![namespace.clone()],
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                (None, Some(namespaces)) => namespaces.clone(),
+                (None, None) => {
+                    let nss: Api<Namespace> = Api::all(client.clone());
+                    match list_metadata_paged(&nss, &ListParams::default()).await {
+                        Ok(namespaces) => namespaces
+                            .iter()
+                            .filter_map(|namespace| namespace.name().map(|name| name.to_string()))
+                            .filter(|name| !import_filter.skip_namespaces.contains(name))
+                            .collect(),
+                        Err(e) if is_forbidden(&e) => {
+                            forbidden.add("Namespace", None);
+                            Vec::new()
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            };
 
-            list!(cluster, client, res, PersistentVolume);
+            let mut tasks: Vec<ListTask> = Vec::new();
 
-            let nss: Api<Namespace> = Api::all(client.clone());
-            let namespaces = nss.list_metadata(&ListParams::default()).await?;
+            // A namespace-scoped cluster config usually means we lack cluster-wide permissions,
+            // so only list cluster-scoped kinds when the whole cluster is in scope.
+            if cluster_config.namespaces.is_none() {
+                if scope.includes_cluster_scoped() {
+                    list_filtered!(
+                        cluster,
+                        client,
+                        tasks,
+                        scope,
+                        import_filter,
+                        ClusterRole,
+                        |name: &Cow<str>, _| !name.starts_with("system:")
+                    );
 
-            for namespace in &namespaces.items {
-                let Some(namespace_name) = namespace.name() else { continue };
+                    list_filtered!(
+                        cluster,
+                        client,
+                        tasks,
+                        scope,
+                        import_filter,
+                        ClusterRoleBinding,
+                        |name: &Cow<str>, _| !name.starts_with("system:")
+                    );
+
+                    list!(cluster, client, tasks, scope, import_filter, PersistentVolume);
+                    list!(cluster, client, tasks, scope, import_filter, IngressClass);
+                    list!(cluster, client, tasks, scope, import_filter, StorageClass);
+                    list!(cluster, client, tasks, scope, import_filter, CSIDriver);
+                    list!(cluster, client, tasks, scope, import_filter, ValidatingWebhookConfiguration);
+                    list!(cluster, client, tasks, scope, import_filter, MutatingWebhookConfiguration);
+                    list!(cluster, client, tasks, scope, import_filter, ValidatingAdmissionPolicy);
+                    list!(cluster, client, tasks, scope, import_filter, ValidatingAdmissionPolicyBinding);
+                    list_filtered!(
+                        cluster,
+                        client,
+                        tasks,
+                        scope,
+                        import_filter,
+                        PriorityClass,
+                        |name: &Cow<str>, _| !name.starts_with("system-")
+                    );
+                    list!(cluster, client, tasks, scope, import_filter, RuntimeClass);
+                    if scope.includes_kind("node") {
+                        tasks.push(ListTask::new(String::from("Node"), self.list_nodes(&cluster, &client)));
+                    }
+                }
+            }
+
+            // VolumeSnapshotClass and VolumeSnapshot are CRDs (snapshot.storage.k8s.io),
+            // so when the snapshot controller is installed they're listed here as dynamic resources.
+            // Namespaced custom resources are listed even with a namespace-scoped cluster config,
+            // as long as the CRDs themselves can be read.
+            if scope.includes_custom_resources() {
+                match self
+                    .list_custom_resources(&cluster, &client, &scope, &namespace_names, import_filter, &mut tasks)
+                    .await
+                {
+                    Ok(crds) => res.extend(crds),
+                    Err(e) if is_forbidden(&e) => forbidden.add("CustomResourceDefinition", None),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let secrets_enabled = self.secrets_enabled().await;
+
+            for namespace_name in &namespace_names {
                 res.push(
                     K8sClusterAddress {
                         cluster: cluster.clone(),
@@ -112,22 +497,237 @@ impl K8sConnector {
                     .to_path_buf(),
                 );
 
-                list!(cluster, client, res, Pod, namespace_name);
-                list!(cluster, client, res, Service, namespace_name);
-                list!(cluster, client, res, Deployment, namespace_name);
-                list!(cluster, client, res, ConfigMap, namespace_name);
-                // list!(cluster, client, res, Secret, namespace_name);
-                list!(cluster, client, res, PersistentVolumeClaim, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, Pod, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, Service, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, Deployment, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, StatefulSet, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, DaemonSet, namespace_name);
+                // ReplicaSets owned by a Deployment and Jobs owned by a CronJob are left out by the import filter.
+                list!(cluster, client, tasks, scope, import_filter, ReplicaSet, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, Job, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, CronJob, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, ConfigMap, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, Ingress, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, NetworkPolicy, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, ResourceQuota, namespace_name);
+                list!(cluster, client, tasks, scope, import_filter, LimitRange, namespace_name);
+                list!(
+                    cluster,
+                    client,
+                    tasks,
+                    scope,
+                    import_filter,
+                    HorizontalPodAutoscaler,
+                    namespace_name
+                );
+                list!(
+                    cluster,
+                    client,
+                    tasks,
+                    scope,
+                    import_filter,
+                    PodDisruptionBudget,
+                    namespace_name
+                );
+
+                // Every namespace gets a `default` ServiceAccount, so only import it if it's been customized.
+                list_filtered!(
+                    cluster,
+                    client,
+                    tasks,
+                    scope,
+                    import_filter,
+                    ServiceAccount,
+                    namespace_name,
+                    |name: &Cow<str>, _| { *name != "default" }
+                );
+                if scope.includes_kind("serviceaccount") {
+                    let cluster = cluster.clone();
+                    let client = client.clone();
+                    tasks.push(ListTask::namespaced(
+                        String::from("ServiceAccount"),
+                        namespace_name,
+                        async move {
+                            if !self.default_service_account_is_customized(&client, namespace_name).await? {
+                                return Ok(Vec::new());
+                            }
+                            Ok(vec![
+                                K8sClusterAddress {
+                                    cluster,
+                                    res_addr: K8sResourceAddress::ServiceAccount(
+                                        namespace_name.to_string(),
+                                        String::from("default"),
+                                    ),
+                                }
+                                .to_path_buf(),
+                            ])
+                        },
+                    ));
+                }
+
+                if scope.includes_kind("secret") && secrets_enabled {
+                    tasks.push(ListTask::namespaced(
+                        String::from("Secret"),
+                        namespace_name,
+                        self.list_secrets(&cluster, &client, namespace_name, import_filter),
+                    ));
+                }
+                list!(
+                    cluster,
+                    client,
+                    tasks,
+                    scope,
+                    import_filter,
+                    PersistentVolumeClaim,
+                    namespace_name
+                );
                 // list!(cluster, client, res, Role, namespace_name);
                 // list!(cluster, client, res, RoleBinding, namespace_name);
-                list_filtered!(cluster, client, res, Role, namespace_name, |name: &Cow<str>| !name
-                    .starts_with("system:"));
+                list_filtered!(
+                    cluster,
+                    client,
+                    tasks,
+                    scope,
+                    import_filter,
+                    Role,
+                    namespace_name,
+                    |name: &Cow<str>, _| { !name.starts_with("system:") }
+                );
+
+                list_filtered!(
+                    cluster,
+                    client,
+                    tasks,
+                    scope,
+                    import_filter,
+                    RoleBinding,
+                    namespace_name,
+                    |name: &Cow<str>, _| !name.starts_with("system:")
+                );
+            }
+
+            res.extend(
+                self.run_list_tasks(&cluster, &cluster_config, namespace_names.len(), tasks, &mut forbidden)
+                    .await?,
+            );
+
+            if !forbidden.is_empty() {
+                let hint = if forbidden.contains("Namespace") {
+                    "\nSet `namespaces` in the cluster config to list namespaced kinds without listing Namespaces."
+                } else {
+                    ""
+                };
+                self.progress(format!(
+                    "Warning: skipped forbidden kinds in cluster {}: {}{}",
+                    cluster, forbidden, hint
+                ));
+            }
+
+            // Objects declared in a bundle are managed through the bundle file, not as standalone files.
+            let bundled = self.bundled_paths(&cluster);
+            res.retain(|path| !bundled.contains(path));
+        }
+
+        // Resources that are already kept as JSON in the repo are imported back into the same file.
+        for path in &mut res {
+            let json_path = path.with_extension("json");
+            if self.prefix.join(&json_path).is_file() {
+                *path = json_path;
+            }
+        }
+
+        // The scope only decides which APIs to query; trim the results to exactly what's under the subpath.
+        let subpath: PathBuf = subpath.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+        res.retain(|path| path.starts_with(&subpath));
+
+        // Queries finish in whatever order the API server answers them.
+        res.sort();
+        res.dedup();
+
+        Ok(res)
+    }
+
+    /// Run a cluster's list queries, at most `list_concurrency` at a time,
+    /// reporting progress to the outbox as they complete.
+    /// Forbidden queries are added to `forbidden` for the caller to report; any other error fails the listing.
+    async fn run_list_tasks(
+        &self,
+        cluster: &str,
+        cluster_config: &K8sClusterConfig,
+        namespace_count: usize,
+        tasks: Vec<ListTask<'_>>,
+        forbidden: &mut ForbiddenKinds,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let concurrency = cluster_config.list_concurrency.unwrap_or(DEFAULT_LIST_CONCURRENCY).max(1);
+        let total = tasks.len();
+
+        self.progress(format!(
+            "Listing cluster {}: {} queries across {} namespaces",
+            cluster, total, namespace_count
+        ));
 
-                list_filtered!(cluster, client, res, RoleBinding, namespace_name, |name: &Cow<str>| !name
-                    .starts_with("system:"));
+        let mut res = Vec::new();
+        let mut results = stream::iter(tasks)
+            .map(|task| async move { (task.kind, task.namespace, task.query.await) })
+            .buffer_unordered(concurrency);
+        let mut done = 0;
+        while let Some((kind, namespace, paths)) = results.next().await {
+            match paths {
+                Ok(paths) => res.extend(paths),
+                Err(e) if is_forbidden(&e) => forbidden.add(&kind, namespace.as_deref()),
+                Err(e) => {
+                    let label = match namespace {
+                        Some(namespace) => format!("{} in namespace {}", kind, namespace),
+                        None => kind,
+                    };
+                    return Err(e.context(format!("Failed to list {} in cluster {}", label, cluster)));
+                }
+            }
+            done += 1;
+            // Report roughly every tenth of the way, rather than once per query.
+            if done < total && done % total.div_ceil(10) == 0 {
+                self.progress(format!("Listing cluster {}: {}/{} queries done", cluster, done, total));
             }
         }
 
+        self.progress(format!("Listed cluster {}: {} resources", cluster, res.len()));
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_custom_resource_scopes_list_crds() {
+        let scope = |path: &str| ListScope::from_subpath(Path::new(path)).unwrap();
+
+        for path in ["", "k8s/prod", "k8s/prod/ns/payments", "k8s/prod/customresourcedefinition"] {
+            assert!(scope(path).includes_custom_resources(), "{}", path);
+        }
+        assert!(scope("k8s/prod/ns/payments/cert-manager.io").includes_custom_resources());
+        assert!(!scope("k8s/prod/ns/payments/deployment").includes_custom_resources());
+        assert!(!scope("k8s/prod/clusterrole").includes_custom_resources());
+    }
+
+    #[test]
+    fn forbidden_kinds_are_grouped() {
+        let mut forbidden = ForbiddenKinds::default();
+        assert!(forbidden.is_empty());
+
+        for namespace in ["payments", "web", "default"] {
+            forbidden.add("Secret", Some(namespace));
+        }
+        forbidden.add("ServiceAccount", Some("web"));
+        forbidden.add("ServiceAccount", Some("web"));
+        forbidden.add("Node", None);
+        assert!(forbidden.contains("Node"));
+        assert!(!forbidden.contains("Namespace"));
+
+        assert_eq!(
+            forbidden.to_string(),
+            "Node, Secret (3 namespaces), ServiceAccount (1 namespace)"
+        );
+    }
+}
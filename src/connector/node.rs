@@ -0,0 +1,397 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::bail;
+use autoschematic_core::{
+    connector::{GetResourceResponse, OpExecResponse, PlanResponseElement, ResourceAddress},
+    connector_op,
+};
+use k8s_openapi::{
+    api::core::v1::{Node, NodeSpec, Taint},
+    serde_json,
+};
+use kube::{
+    Api, Client,
+    api::{ListParams, ObjectMeta, Patch, PatchParams},
+    runtime::reflector::Lookup,
+};
+
+use crate::{
+    addr::{K8sClusterAddress, K8sResourceAddress},
+    op::K8sConnectorOp,
+    util::{SERDE, diff_yaml_values, from_str_option},
+};
+
+use super::{K8sConnector, list::list_metadata_paged};
+
+/// Nodes are only ever touched through server-side apply under this field manager,
+/// and only the fields it owns are read back.
+const NODE_FIELD_MANAGER: &str = "autoschematic";
+
+/// Taints are an atomic list in server-side apply, so managedFields can't say which of them autoschematic
+/// declared. Their (key, effect) pairs are recorded in this annotation instead, e.g. `gpu:NoSchedule,spot:NoExecute`.
+const MANAGED_TAINTS_ANNOTATION: &str = "autoschematic.io/managed-taints";
+
+/// The labels, annotations and taints that a field manager has applied to a Node, according to its managedFields.
+#[derive(Debug, Default)]
+struct OwnedNodeFields {
+    labels: Vec<String>,
+    annotations: Vec<String>,
+    /// Taints are keyed by (key, effect), which is their merge key.
+    taints: Vec<(String, String)>,
+}
+
+impl OwnedNodeFields {
+    fn from_managed_fields(meta: &ObjectMeta, manager: &str) -> Self {
+        let mut owned = Self::default();
+
+        for entry in meta.managed_fields.iter().flatten() {
+            if entry.manager.as_deref() != Some(manager) || entry.operation.as_deref() != Some("Apply") {
+                continue;
+            }
+            let Some(fields) = &entry.fields_v1 else { continue };
+            let fields = &fields.0;
+
+            owned.labels.extend(field_keys(&fields["f:metadata"]["f:labels"]));
+            owned.annotations.extend(field_keys(&fields["f:metadata"]["f:annotations"]));
+        }
+
+        if owned.annotations.iter().any(|a| a == MANAGED_TAINTS_ANNOTATION) {
+            owned.annotations.retain(|a| a != MANAGED_TAINTS_ANNOTATION);
+            owned.taints = managed_taints(meta);
+        }
+
+        owned
+    }
+
+    fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.annotations.is_empty() && self.taints.is_empty()
+    }
+}
+
+/// The names of the fields in a FieldsV1 map, e.g. `{"f:team": {}}` -> `["team"]`.
+fn field_keys(fields: &serde_json::Value) -> Vec<String> {
+    fields
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(k, _)| k.strip_prefix("f:"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The (key, effect) pairs recorded in a Node's managed taints annotation.
+fn managed_taints(meta: &ObjectMeta) -> Vec<(String, String)> {
+    meta.annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(MANAGED_TAINTS_ANNOTATION))
+        .into_iter()
+        .flat_map(|v| v.split(','))
+        .filter_map(|taint| taint.split_once(':'))
+        .map(|(key, effect)| (key.to_string(), effect.to_string()))
+        .collect()
+}
+
+fn taint_matches(taint: &Taint, keys: &[(String, String)]) -> bool {
+    keys.iter().any(|(k, e)| *k == taint.key && *e == taint.effect)
+}
+
+/// Reduce a Node to just its name, labels, annotations and taints,
+/// keeping only the entries for which `keep_*` returns true.
+fn node_view(
+    node: &Node,
+    keep_label: impl Fn(&str) -> bool,
+    keep_annotation: impl Fn(&str) -> bool,
+    keep_taint: impl Fn(&Taint) -> bool,
+) -> Node {
+    let pick = |map: &Option<BTreeMap<String, String>>, keep: &dyn Fn(&str) -> bool| {
+        let picked: BTreeMap<String, String> = map
+            .iter()
+            .flatten()
+            .filter(|(k, _)| keep(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        (!picked.is_empty()).then_some(picked)
+    };
+
+    let taints: Vec<Taint> = node
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.taints.as_ref())
+        .into_iter()
+        .flatten()
+        .filter(|taint| keep_taint(taint))
+        .cloned()
+        .collect();
+
+    Node {
+        metadata: ObjectMeta {
+            name: node.metadata.name.clone(),
+            labels: pick(&node.metadata.labels, &keep_label),
+            annotations: pick(&node.metadata.annotations, &keep_annotation),
+            ..Default::default()
+        },
+        spec: (!taints.is_empty()).then(|| NodeSpec {
+            taints: Some(taints),
+            ..Default::default()
+        }),
+        status: None,
+    }
+}
+
+/// The part of a live Node that autoschematic owns.
+fn owned_node_view(node: &Node) -> Node {
+    let owned = OwnedNodeFields::from_managed_fields(&node.metadata, NODE_FIELD_MANAGER);
+    node_view(
+        node,
+        |k| owned.labels.iter().any(|l| l == k),
+        |k| owned.annotations.iter().any(|a| a == k),
+        |taint| taint_matches(taint, &owned.taints),
+    )
+}
+
+/// The part of a Node file that's applied: anything other than labels, annotations and taints is ignored.
+fn node_apply_config(node: &Node) -> Node {
+    node_view(node, |_| true, |k| k != MANAGED_TAINTS_ANNOTATION, |_| true)
+}
+
+/// Build the config that's actually applied to a live Node. Because the taints list is atomic,
+/// applying only the declared taints would drop every taint set by the kubelet or a controller
+/// (e.g. `node.kubernetes.io/unschedulable`). Instead, the live taints that autoschematic doesn't manage
+/// are carried over, and the declared ones are recorded in the managed taints annotation.
+fn merge_node_apply_config(live: &Node, mut config: Node) -> Node {
+    let declared: Vec<Taint> = config.spec.take().and_then(|spec| spec.taints).unwrap_or_default();
+    let declared_keys: Vec<(String, String)> = declared.iter().map(|t| (t.key.clone(), t.effect.clone())).collect();
+    let previous_keys = managed_taints(&live.metadata);
+
+    let live_taints = live.spec.as_ref().and_then(|spec| spec.taints.as_ref());
+    let mut taints: Vec<Taint> = live_taints
+        .into_iter()
+        .flatten()
+        .filter(|t| !taint_matches(t, &previous_keys) && !taint_matches(t, &declared_keys))
+        .cloned()
+        .collect();
+    taints.extend(declared);
+
+    if live_taints.is_some_and(|t| !t.is_empty()) || !taints.is_empty() {
+        config.spec = Some(NodeSpec {
+            taints: Some(taints),
+            ..Default::default()
+        });
+    }
+
+    if !declared_keys.is_empty() {
+        let value = declared_keys
+            .iter()
+            .map(|(key, effect)| format!("{}:{}", key, effect))
+            .collect::<Vec<_>>()
+            .join(",");
+        config
+            .metadata
+            .annotations
+            .get_or_insert_default()
+            .insert(MANAGED_TAINTS_ANNOTATION.to_string(), value);
+    }
+
+    // Fail rather than overwrite taints that changed since they were read.
+    config.metadata.resource_version = live.metadata.resource_version.clone();
+
+    config
+}
+
+impl K8sConnector {
+    /// List the Nodes that autoschematic owns any labels, annotations or taints on.
+    pub async fn list_nodes(&self, cluster: &str, client: &Client) -> anyhow::Result<Vec<PathBuf>> {
+        let mut res = Vec::new();
+
+        let nodes: Api<Node> = Api::all(client.clone());
+        for node in list_metadata_paged(&nodes, &ListParams::default()).await? {
+            let Some(name) = node.name() else { continue };
+            if OwnedNodeFields::from_managed_fields(&node.metadata, NODE_FIELD_MANAGER).is_empty() {
+                continue;
+            }
+            res.push(
+                K8sClusterAddress {
+                    cluster: cluster.to_string(),
+                    res_addr: K8sResourceAddress::Node(name.to_string()),
+                }
+                .to_path_buf(),
+            );
+        }
+
+        Ok(res)
+    }
+
+    /// Fetch a Node, keeping only the labels, annotations and taints owned by autoschematic.
+    pub async fn get_node(&self, client: &Client, name: &str) -> anyhow::Result<Option<GetResourceResponse>> {
+        let nodes: Api<Node> = Api::all(client.clone());
+        let Some(node) = nodes.get_opt(name).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(GetResourceResponse {
+            resource_definition: SERDE.to_string(&owned_node_view(&node))?.into_bytes(),
+            outputs: None,
+        }))
+    }
+
+    /// Plan a Node. Nodes are never created or deleted: every op is an apply of the owned fields,
+    /// and removing the file applies an empty config, which releases (and removes) them.
+    pub fn plan_node(
+        &self,
+        name: &str,
+        current: &Option<Vec<u8>>,
+        desired: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Option<PlanResponseElement>> {
+        let current: Option<Node> = from_str_option(current)?;
+        let desired: Option<Node> = from_str_option(desired)?;
+
+        let op = match (current, desired) {
+            (None, Some(desired)) => Some(connector_op!(
+                K8sConnectorOp::Patch(SERDE.to_string(&node_apply_config(&desired))?),
+                format!("Apply labels, annotations and taints to Node {}", name)
+            )),
+
+            (Some(current), None) => {
+                let release = Node {
+                    metadata: ObjectMeta {
+                        name: current.metadata.name.clone(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                Some(connector_op!(
+                    K8sConnectorOp::Patch(SERDE.to_string(&release)?),
+                    format!("Remove owned labels, annotations and taints from Node {}", name)
+                ))
+            }
+
+            (Some(current), Some(desired)) => {
+                let current = node_apply_config(&current);
+                let desired = node_apply_config(&desired);
+                if current == desired {
+                    None
+                } else {
+                    let diff = diff_yaml_values(&current, &desired)?;
+                    Some(connector_op!(
+                        K8sConnectorOp::Patch(SERDE.to_string(&desired)?),
+                        format!("Modify Node {}:\n{}", name, diff)
+                    ))
+                }
+            }
+            _ => None,
+        };
+
+        Ok(op)
+    }
+
+    pub async fn op_exec_node(&self, client: &Client, name: &str, op: K8sConnectorOp) -> anyhow::Result<OpExecResponse> {
+        let api: Api<Node> = Api::all(client.clone());
+
+        let K8sConnectorOp::Patch(resource) = op else {
+            bail!(
+                "Node {} can't be created, replaced or deleted; only its labels, annotations and taints are applied",
+                name
+            );
+        };
+
+        // Applying to a missing Node would create it, but Nodes are registered by their kubelet.
+        let Some(live) = api.get_opt(name).await? else {
+            bail!("Node {} does not exist in the cluster", name);
+        };
+
+        let resource = merge_node_apply_config(&live, SERDE.from_str(&resource)?);
+
+        // Node labels and taints are often set imperatively (kubectl label/taint) before being
+        // declared here, so take ownership of them rather than failing on a conflict.
+        // Taints that aren't declared are carried over by merge_node_apply_config.
+        let patch_params = PatchParams::apply(NODE_FIELD_MANAGER).force();
+        api.patch(name, &patch_params, &Patch::Apply(resource)).await?;
+
+        Ok(OpExecResponse {
+            outputs: None,
+            friendly_message: Some(format!("Applied labels, annotations and taints to Node {}", name)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taint(key: &str, effect: &str) -> Taint {
+        Taint {
+            key: key.to_string(),
+            effect: effect.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn node(taints: Vec<Taint>, managed: Option<&str>) -> Node {
+        Node {
+            metadata: ObjectMeta {
+                name: Some(String::from("worker-1")),
+                annotations: managed.map(|m| BTreeMap::from([(MANAGED_TAINTS_ANNOTATION.to_string(), m.to_string())])),
+                resource_version: Some(String::from("42")),
+                ..Default::default()
+            },
+            spec: Some(NodeSpec {
+                taints: Some(taints),
+                ..Default::default()
+            }),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn apply_keeps_unmanaged_taints() {
+        let live = node(
+            vec![
+                taint("node.kubernetes.io/unschedulable", "NoSchedule"),
+                taint("gpu", "NoSchedule"),
+            ],
+            Some("gpu:NoSchedule"),
+        );
+        let config = node_apply_config(&node(vec![taint("spot", "NoExecute")], None));
+
+        let applied = merge_node_apply_config(&live, config);
+
+        assert_eq!(
+            applied.spec.unwrap().taints.unwrap(),
+            vec![
+                taint("node.kubernetes.io/unschedulable", "NoSchedule"),
+                taint("spot", "NoExecute")
+            ]
+        );
+        assert_eq!(
+            applied.metadata.annotations.unwrap()[MANAGED_TAINTS_ANNOTATION],
+            "spot:NoExecute"
+        );
+        assert_eq!(applied.metadata.resource_version.as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn release_removes_only_managed_taints() {
+        let live = node(
+            vec![
+                taint("node.kubernetes.io/unschedulable", "NoSchedule"),
+                taint("gpu", "NoSchedule"),
+            ],
+            Some("gpu:NoSchedule"),
+        );
+        let release = Node {
+            metadata: ObjectMeta {
+                name: Some(String::from("worker-1")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let applied = merge_node_apply_config(&live, release);
+
+        assert_eq!(
+            applied.spec.unwrap().taints.unwrap(),
+            vec![taint("node.kubernetes.io/unschedulable", "NoSchedule")]
+        );
+        assert!(applied.metadata.annotations.is_none());
+    }
+}
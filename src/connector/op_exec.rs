@@ -4,25 +4,42 @@ use autoschematic_core::{
         Connector, ConnectorOp, ConnectorOutbox, GetResourceResponse, OpExecResponse, PlanResponseElement, ResourceAddress,
     },
     connector_op, op_exec_output,
-    util::{PrettyConfig, RON, diff_ron_values, ron_check_eq, ron_check_syntax},
 };
-use k8s_openapi::api::{
-    apps::v1::Deployment,
-    core::v1::{ConfigMap, Namespace, NamespaceSpec, PersistentVolume, PersistentVolumeClaim, Pod, Secret, Service},
-    rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+use k8s_openapi::{
+    api::{
+        admissionregistration::v1::{
+            MutatingWebhookConfiguration, ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding,
+            ValidatingWebhookConfiguration,
+        },
+        apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+        autoscaling::v2::HorizontalPodAutoscaler,
+        batch::v1::{CronJob, Job},
+        core::v1::{
+            ConfigMap, LimitRange, Namespace, NamespaceSpec, PersistentVolume, PersistentVolumeClaim, Pod, ResourceQuota,
+            Secret, Service, ServiceAccount,
+        },
+        networking::v1::{Ingress, IngressClass, NetworkPolicy},
+        node::v1::RuntimeClass,
+        policy::v1::PodDisruptionBudget,
+        rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+        scheduling::v1::PriorityClass,
+        storage::v1::{CSIDriver, StorageClass},
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
 };
 use kube::{
     Api, Client,
     api::{DeleteParams, ListParams, PatchParams, PostParams},
     client,
-    runtime::reflector::Lookup,
+    runtime::{reflector::Lookup, wait::delete::delete_and_finalize},
 };
 use serde::Serialize;
 
 use crate::{
     addr::{K8sClusterAddress, K8sResourceAddress},
+    neat::neatify_job,
     op::K8sConnectorOp,
-    util::{from_str_option, strip_boring_fields},
+    util::{SERDE, from_str_option, strip_boring_fields},
 };
 use std::path::Path;
 
@@ -30,6 +47,14 @@ use std::collections::HashMap;
 
 use super::K8sConnector;
 
+fn strip_job_selector(resource: &str) -> anyhow::Result<String> {
+    let mut v: serde_yaml::Value = SERDE.from_str(resource)?;
+    if let Some(obj) = v.as_mapping_mut() {
+        neatify_job(obj);
+    }
+    SERDE.to_string(&v)
+}
+
 macro_rules! create_delete_patch {
     ($type:ty, $name:expr, $client:expr, $op:expr) => {{
         let api: Api<$type> = Api::all($client.clone());
@@ -46,7 +71,7 @@ macro_rules! create_delete_patch {
 
         match $op {
             K8sConnectorOp::Create(resource) => {
-                let resource: $type = RON.from_str(&resource)?;
+                let resource: $type = SERDE.from_str(&resource)?;
                 api.create(&post_params, &resource).await?;
                 OpExecResponse {
                     outputs: None,
@@ -54,7 +79,7 @@ macro_rules! create_delete_patch {
                 }
             }
             K8sConnectorOp::Patch(resource) => {
-                let resource: $type = RON.from_str(&resource)?;
+                let resource: $type = SERDE.from_str(&resource)?;
                 api.patch($name, &patch_params, &kube::api::Patch::Apply(resource))
                     .await?;
                 OpExecResponse {
@@ -62,6 +87,15 @@ macro_rules! create_delete_patch {
                     friendly_message: Some(format!("Modified {} {}", stringify!($type), $name)),
                 }
             }
+            K8sConnectorOp::Replace(resource) => {
+                let resource: $type = SERDE.from_str(&resource)?;
+                delete_and_finalize(api.clone(), $name, &DeleteParams::background()).await?;
+                api.create(&post_params, &resource).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Replaced {} {}", stringify!($type), $name)),
+                }
+            }
             K8sConnectorOp::Delete => {
                 api.delete($name, &DeleteParams::default()).await?;
                 OpExecResponse {
@@ -86,7 +120,7 @@ macro_rules! create_delete_patch {
 
         match $op {
             K8sConnectorOp::Create(resource) => {
-                let resource: $type = RON.from_str(&resource)?;
+                let resource: $type = SERDE.from_str(&resource)?;
                 api.create(&post_params, &resource).await?;
                 OpExecResponse {
                     outputs: None,
@@ -94,7 +128,7 @@ macro_rules! create_delete_patch {
                 }
             }
             K8sConnectorOp::Patch(resource) => {
-                let resource: $type = RON.from_str(&resource)?;
+                let resource: $type = SERDE.from_str(&resource)?;
                 api.patch($name, &patch_params, &kube::api::Patch::Apply(resource))
                     .await?;
                 OpExecResponse {
@@ -102,6 +136,15 @@ macro_rules! create_delete_patch {
                     friendly_message: Some(format!("Modified {} {}", stringify!($type), $name)),
                 }
             }
+            K8sConnectorOp::Replace(resource) => {
+                let resource: $type = SERDE.from_str(&resource)?;
+                delete_and_finalize(api.clone(), $name, &DeleteParams::background()).await?;
+                api.create(&post_params, &resource).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Replaced {} {}", stringify!($type), $name)),
+                }
+            }
             K8sConnectorOp::Delete => {
                 api.delete($name, &DeleteParams::default()).await?;
                 OpExecResponse {
@@ -117,6 +160,11 @@ impl K8sConnector {
     pub async fn do_op_exec(&self, addr: &Path, op: &str) -> Result<OpExecResponse, anyhow::Error> {
         let addr = K8sClusterAddress::from_path(addr)?;
 
+        // Bundle ops carry their member's own address and op.
+        if let K8sResourceAddress::Bundle(_) = addr.res_addr {
+            return self.op_exec_bundle(op).await;
+        }
+
         let op = K8sConnectorOp::from_str(op)?;
 
         let client = (*self.get_or_init_client(&addr.cluster).await?).clone();
@@ -134,9 +182,55 @@ impl K8sConnector {
             K8sResourceAddress::Deployment(namespace, name) => {
                 create_delete_patch!(Deployment, namespace, name, client, op)
             }
+            K8sResourceAddress::StatefulSet(namespace, name) => {
+                create_delete_patch!(StatefulSet, namespace, name, client, op)
+            }
+            K8sResourceAddress::DaemonSet(namespace, name) => {
+                create_delete_patch!(DaemonSet, namespace, name, client, op)
+            }
+            K8sResourceAddress::ReplicaSet(namespace, name) => {
+                create_delete_patch!(ReplicaSet, namespace, name, client, op)
+            }
+            K8sResourceAddress::Job(namespace, name) => {
+                // Job files imported before the generated selector was stripped still carry it,
+                // which the API server rejects when the Job is (re)created.
+                let op = match op {
+                    K8sConnectorOp::Create(resource) => K8sConnectorOp::Create(strip_job_selector(&resource)?),
+                    K8sConnectorOp::Replace(resource) => K8sConnectorOp::Replace(strip_job_selector(&resource)?),
+                    op => op,
+                };
+                create_delete_patch!(Job, namespace, name, client, op)
+            }
+            K8sResourceAddress::CronJob(namespace, name) => {
+                create_delete_patch!(CronJob, namespace, name, client, op)
+            }
             K8sResourceAddress::ConfigMap(namespace, name) => {
                 create_delete_patch!(ConfigMap, namespace, name, client, op)
             }
+            K8sResourceAddress::Ingress(namespace, name) => {
+                create_delete_patch!(Ingress, namespace, name, client, op)
+            }
+            K8sResourceAddress::NetworkPolicy(namespace, name) => {
+                create_delete_patch!(NetworkPolicy, namespace, name, client, op)
+            }
+            K8sResourceAddress::ResourceQuota(namespace, name) => {
+                create_delete_patch!(ResourceQuota, namespace, name, client, op)
+            }
+            K8sResourceAddress::LimitRange(namespace, name) => {
+                create_delete_patch!(LimitRange, namespace, name, client, op)
+            }
+            K8sResourceAddress::ServiceAccount(namespace, name) => {
+                create_delete_patch!(ServiceAccount, namespace, name, client, op)
+            }
+            K8sResourceAddress::HorizontalPodAutoscaler(namespace, name) => {
+                let output = create_delete_patch!(HorizontalPodAutoscaler, namespace, name, client, op);
+                self.forget_namespace_hpas(&addr.cluster, namespace).await;
+                output
+            }
+            K8sResourceAddress::PodDisruptionBudget(namespace, name) => {
+                create_delete_patch!(PodDisruptionBudget, namespace, name, client, op)
+            }
+            K8sResourceAddress::Secret(namespace, name) => self.op_exec_secret(&client, namespace, name, op).await?,
             K8sResourceAddress::PersistentVolumeClaim(namespace, name) => {
                 create_delete_patch!(PersistentVolumeClaim, namespace, name, client, op)
             }
@@ -154,14 +248,63 @@ impl K8sConnector {
             }
             K8sResourceAddress::ClusterRoleBinding(name) => {
                 create_delete_patch!(ClusterRoleBinding, name, client, op)
-            } // K8sResourceAddress::Binding(_, _) => todo!(),
-              // K8sResourceAddress::Endpoints(_, _) => todo!(),
-              // K8sResourceAddress::LimitRange(_, _) => todo!(),
-              // K8sResourceAddress::Node(_, _) => todo!(),
-              // K8sResourceAddress::PodTemplate(_, _) => todo!(),
-              // K8sResourceAddress::ReplicationController(_, _) => todo!(),
-              // K8sResourceAddress::ResourceQuota(_, _) => todo!(),
-              // K8sResourceAddress::ServiceAccount(_, _) => todo!(),
+            }
+            K8sResourceAddress::IngressClass(name) => {
+                create_delete_patch!(IngressClass, name, client, op)
+            }
+            K8sResourceAddress::StorageClass(name) => {
+                create_delete_patch!(StorageClass, name, client, op)
+            }
+            K8sResourceAddress::CSIDriver(name) => {
+                create_delete_patch!(CSIDriver, name, client, op)
+            }
+            K8sResourceAddress::ValidatingWebhookConfiguration(name) => {
+                create_delete_patch!(ValidatingWebhookConfiguration, name, client, op)
+            }
+            K8sResourceAddress::MutatingWebhookConfiguration(name) => {
+                create_delete_patch!(MutatingWebhookConfiguration, name, client, op)
+            }
+            K8sResourceAddress::ValidatingAdmissionPolicy(name) => {
+                create_delete_patch!(ValidatingAdmissionPolicy, name, client, op)
+            }
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(name) => {
+                create_delete_patch!(ValidatingAdmissionPolicyBinding, name, client, op)
+            }
+            K8sResourceAddress::PriorityClass(name) => {
+                create_delete_patch!(PriorityClass, name, client, op)
+            }
+            K8sResourceAddress::RuntimeClass(name) => {
+                create_delete_patch!(RuntimeClass, name, client, op)
+            }
+            K8sResourceAddress::Node(name) => self.op_exec_node(&client, name, op).await?,
+            K8sResourceAddress::CustomResourceDefinition(name) => {
+                let output = create_delete_patch!(CustomResourceDefinition, name, client, op);
+                self.forget_installed_crds(&addr.cluster).await;
+                output
+            }
+            K8sResourceAddress::Bundle(_) => unreachable!("bundle ops are routed to their members above"),
+            K8sResourceAddress::Dynamic(namespace, group, version, kind, name) => {
+                if matches!(op, K8sConnectorOp::Create(_)) {
+                    self.wait_for_declared_crd(&addr.cluster, &client, group, version, kind)
+                        .await?;
+                }
+                let (api, ar) = self
+                    .dynamic_api(&addr.cluster, &client, Some(namespace.as_str()), group, version, kind)
+                    .await?;
+                self.op_exec_dynamic(api, &ar, name, op).await?
+            }
+            K8sResourceAddress::ClusterDynamic(group, version, kind, name) => {
+                if matches!(op, K8sConnectorOp::Create(_)) {
+                    self.wait_for_declared_crd(&addr.cluster, &client, group, version, kind)
+                        .await?;
+                }
+                let (api, ar) = self.dynamic_api(&addr.cluster, &client, None, group, version, kind).await?;
+                self.op_exec_dynamic(api, &ar, name, op).await?
+            }
+            // K8sResourceAddress::Binding(_, _) => todo!(),
+            // K8sResourceAddress::Endpoints(_, _) => todo!(),
+            // K8sResourceAddress::PodTemplate(_, _) => todo!(),
+            // K8sResourceAddress::ReplicationController(_, _) => todo!(),
         };
 
         Ok(output)
@@ -3,12 +3,28 @@ use autoschematic_core::{
         Connector, ConnectorOp, ConnectorOutbox, GetResourceResponse, OpExecResponse, PlanResponseElement, ResourceAddress,
     },
     connector_op,
-    util::{PrettyConfig, RON, diff_ron_values, ron_check_eq, ron_check_syntax},
 };
-use k8s_openapi::api::{
-    apps::v1::Deployment,
-    core::v1::{ConfigMap, Namespace, NamespaceSpec, PersistentVolume, PersistentVolumeClaim, Pod, Secret, Service},
-    rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+use k8s_openapi::{
+    api::{
+        admissionregistration::v1::{
+            MutatingWebhookConfiguration, ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding,
+            ValidatingWebhookConfiguration,
+        },
+        apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+        autoscaling::v2::HorizontalPodAutoscaler,
+        batch::v1::{CronJob, Job},
+        core::v1::{
+            ConfigMap, LimitRange, Namespace, NamespaceSpec, PersistentVolume, PersistentVolumeClaim, Pod, ResourceQuota,
+            Secret, Service, ServiceAccount,
+        },
+        networking::v1::{Ingress, IngressClass, NetworkPolicy},
+        node::v1::RuntimeClass,
+        policy::v1::PodDisruptionBudget,
+        rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
+        scheduling::v1::PriorityClass,
+        storage::v1::{CSIDriver, StorageClass},
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
 };
 use kube::{
     Api, Client,
@@ -20,19 +36,19 @@ use serde::Serialize;
 use crate::{
     addr::{K8sClusterAddress, K8sResourceAddress},
     op::K8sConnectorOp,
-    util::{from_str_option, strip_boring_fields},
+    util::{SERDE, diff_yaml_values, from_str_option, parse_quantity, strip_boring_fields},
 };
-use std::path::Path;
+use std::{path::Path, sync::Arc};
 
-use super::K8sConnector;
+use super::{K8sConnector, list::list_paged};
 
 macro_rules! create_delete_patch {
-    ($type:ty, $name:expr, $current:expr, $desired:expr) => {{
+    ($type:ty, $name:expr, $current:expr, $desired:expr, immutable: $immutable:expr, replace_on: $replace_on:expr) => {{
         let current: Option<$type> = from_str_option(&$current)?;
         let desired: Option<$type> = from_str_option(&$desired)?;
         match (current, desired) {
             (None, Some(desired)) => Some(connector_op!(
-                K8sConnectorOp::Create(RON.to_string(&desired)?),
+                K8sConnectorOp::Create(SERDE.to_string(&desired)?),
                 format!("Create {} {}", stringify!($type), $name)
             )),
 
@@ -42,39 +58,213 @@ macro_rules! create_delete_patch {
             )),
 
             (Some(current), Some(desired)) => {
-                let diff = diff_ron_values(&current, &desired)?;
-                Some(connector_op!(
-                    K8sConnectorOp::Patch(RON.to_string_pretty(&desired, PrettyConfig::default())?),
-                    format!("Modify {} {}:\n{}", stringify!($type), $name, diff)
-                ))
+                let diff = diff_yaml_values(&current, &desired)?;
+                let replaced = changed_fields(&current, &desired, $replace_on)?;
+                if !replaced.is_empty() {
+                    Some(connector_op!(
+                        K8sConnectorOp::Replace(SERDE.to_string(&desired)?),
+                        format!(
+                            "Replace {} {} (immutable field(s) changed: {}):\n{}",
+                            stringify!($type),
+                            $name,
+                            replaced.join(", "),
+                            diff
+                        )
+                    ))
+                } else {
+                    let warning = immutable_warning(stringify!($type), &changed_fields(&current, &desired, $immutable)?);
+                    Some(connector_op!(
+                        K8sConnectorOp::Patch(SERDE.to_string(&desired)?),
+                        format!("Modify {} {}:\n{}{}", stringify!($type), $name, diff, warning)
+                    ))
+                }
             }
             _ => None,
         }
     }};
+    ($type:ty, $namespace:expr, $name:expr, $current:expr, $desired:expr, immutable: $immutable:expr, replace_on: $replace_on:expr) => {{
+        create_delete_patch!(
+            $type,
+            format!("{}/{}", $namespace, $name),
+            $current,
+            $desired,
+            immutable: $immutable,
+            replace_on: $replace_on
+        )
+    }};
+    ($type:ty, $name:expr, $current:expr, $desired:expr, immutable: $immutable:expr) => {{
+        create_delete_patch!($type, $name, $current, $desired, immutable: $immutable, replace_on: &[])
+    }};
+    ($type:ty, $namespace:expr, $name:expr, $current:expr, $desired:expr, immutable: $immutable:expr) => {{
+        create_delete_patch!($type, $namespace, $name, $current, $desired, immutable: $immutable, replace_on: &[])
+    }};
+    ($type:ty, $name:expr, $current:expr, $desired:expr, replace_on: $replace_on:expr) => {{
+        create_delete_patch!($type, $name, $current, $desired, immutable: &[], replace_on: $replace_on)
+    }};
+    ($type:ty, $namespace:expr, $name:expr, $current:expr, $desired:expr, replace_on: $replace_on:expr) => {{
+        create_delete_patch!($type, $namespace, $name, $current, $desired, immutable: &[], replace_on: $replace_on)
+    }};
+    ($type:ty, $name:expr, $current:expr, $desired:expr) => {{
+        create_delete_patch!($type, $name, $current, $desired, immutable: &[], replace_on: &[])
+    }};
     ($type:ty, $namespace:expr, $name:expr, $current:expr, $desired:expr) => {{
-        let current: Option<$type> = from_str_option(&$current)?;
-        let desired: Option<$type> = from_str_option(&$desired)?;
-        match (current, desired) {
-            (None, Some(desired)) => Some(connector_op!(
-                K8sConnectorOp::Create(RON.to_string(&desired)?),
-                format!("Create {} {}/{}", stringify!($type), $namespace, $name)
-            )),
+        create_delete_patch!($type, $namespace, $name, $current, $desired, immutable: &[], replace_on: &[])
+    }};
+}
 
-            (Some(_), None) => Some(connector_op!(
-                K8sConnectorOp::Delete,
-                format!("Delete {} {}/{}", stringify!($type), $namespace, $name)
-            )),
+/// A path to a field in a serialized resource, e.g. `&["spec", "serviceName"]`.
+type FieldPath = &'static [&'static str];
 
-            (Some(current), Some(desired)) => {
-                let diff = diff_ron_values(&current, &desired)?;
-                Some(connector_op!(
-                    K8sConnectorOp::Patch(RON.to_string_pretty(&desired, PrettyConfig::default())?),
-                    format!("Modify {} {}/{}:\n{}", stringify!($type), $namespace, $name, diff)
-                ))
+/// Job fields that can't be updated, so changing them deletes and recreates the Job.
+/// The selector isn't one of them: it's generated from the Job's UID, so it's never declared.
+const JOB_REPLACE_FIELDS: &[FieldPath] = &[
+    &["spec", "template"],
+    &["spec", "completionMode"],
+];
+
+/// StatefulSet fields that the API server refuses to update.
+const STATEFULSET_IMMUTABLE_FIELDS: &[FieldPath] = &[
+    &["spec", "volumeClaimTemplates"],
+    &["spec", "serviceName"],
+    &["spec", "selector"],
+    &["spec", "podManagementPolicy"],
+];
+
+/// StorageClass fields that can't be updated, so changing them deletes and recreates the StorageClass.
+/// Existing PersistentVolumes keep the settings they were provisioned with.
+const STORAGECLASS_REPLACE_FIELDS: &[FieldPath] =
+    &[&["provisioner"], &["parameters"], &["reclaimPolicy"], &["volumeBindingMode"]];
+
+/// Which of `fields` differ between `current` and `desired`, as dotted paths.
+fn changed_fields<T: Serialize>(current: &T, desired: &T, fields: &[FieldPath]) -> anyhow::Result<Vec<String>> {
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let current = serde_yaml::to_value(current)?;
+    let desired = serde_yaml::to_value(desired)?;
+
+    let lookup = |v: &serde_yaml::Value, path: FieldPath| {
+        path.iter()
+            .try_fold(v, |v, key| v.get(key))
+            .cloned()
+    };
+
+    Ok(fields
+        .iter()
+        .filter(|path| lookup(&current, **path) != lookup(&desired, **path))
+        .map(|path| path.join("."))
+        .collect())
+}
+
+impl K8sConnector {
+    /// Compare a desired ResourceQuota's hard limits against the live quota's `status.used`.
+    /// This is advisory, so any failure to reach the cluster just means no warnings.
+    async fn quota_usage_warnings(&self, cluster: &str, namespace: &str, name: &str, desired: &Option<Vec<u8>>) -> Vec<String> {
+        let Ok(Some(desired)) = from_str_option::<ResourceQuota>(desired) else {
+            return Vec::new();
+        };
+        let Some(hard) = desired.spec.and_then(|spec| spec.hard) else {
+            return Vec::new();
+        };
+
+        let Ok(client) = self.get_or_init_client(cluster).await else {
+            return Vec::new();
+        };
+        let quotas: Api<ResourceQuota> = Api::namespaced((*client).clone(), namespace);
+        let Ok(Some(live)) = quotas.get_opt(name).await else {
+            return Vec::new();
+        };
+        let Some(used) = live.status.and_then(|status| status.used) else {
+            return Vec::new();
+        };
+
+        let mut warnings = Vec::new();
+        for (resource, limit) in &hard {
+            let Some(usage) = used.get(resource) else { continue };
+            let (Some(limit_n), Some(usage_n)) = (parse_quantity(&limit.0), parse_quantity(&usage.0)) else {
+                continue;
+            };
+            if limit_n < usage_n {
+                warnings.push(format!("  {}: hard limit {} < used {}", resource, limit.0, usage.0));
             }
-            _ => None,
         }
-    }};
+        warnings
+    }
+
+    /// Whether a HorizontalPodAutoscaler targets this Deployment, either live in the cluster
+    /// or declared in the repo. Like the quota check, this is best-effort.
+    pub async fn deployment_is_autoscaled(&self, cluster: &str, namespace: &str, name: &str) -> bool {
+        self.namespace_hpas(cluster, namespace)
+            .await
+            .iter()
+            .any(|hpa| hpa_targets(hpa, "Deployment", name))
+    }
+
+    /// The HPAs in a namespace, both declared in the repo (including in bundles) and live in the cluster.
+    /// They're looked up once per namespace and reused, since every Deployment plan and comparison needs them.
+    async fn namespace_hpas(&self, cluster: &str, namespace: &str) -> Arc<Vec<HorizontalPodAutoscaler>> {
+        let key = (cluster.to_string(), namespace.to_string());
+        if let Some(hpas) = self.hpa_cache.read().await.get(&key) {
+            return hpas.clone();
+        }
+
+        let addr = K8sClusterAddress {
+            cluster: cluster.to_string(),
+            res_addr: K8sResourceAddress::HorizontalPodAutoscaler(namespace.to_string(), String::new()),
+        };
+        let mut hpas: Vec<HorizontalPodAutoscaler> = self
+            .declared_objects(&addr)
+            .into_iter()
+            .filter_map(|v| serde_yaml::from_value(v).ok())
+            .collect();
+
+        if let Ok(client) = self.get_or_init_client(cluster).await {
+            let live: Api<HorizontalPodAutoscaler> = Api::namespaced((*client).clone(), namespace);
+            if let Ok(live) = list_paged(&live, &ListParams::default()).await {
+                hpas.extend(live);
+            }
+        }
+
+        let hpas = Arc::new(hpas);
+        self.hpa_cache.write().await.insert(key, hpas.clone());
+        hpas
+    }
+
+    pub async fn forget_namespace_hpas(&self, cluster: &str, namespace: &str) {
+        self.hpa_cache
+            .write()
+            .await
+            .remove(&(cluster.to_string(), namespace.to_string()));
+    }
+}
+
+/// Whether an HPA scales the object of the given kind and name.
+fn hpa_targets(hpa: &HorizontalPodAutoscaler, kind: &str, name: &str) -> bool {
+    let Some(spec) = &hpa.spec else { return false };
+    spec.scale_target_ref.kind == kind && spec.scale_target_ref.name == name
+}
+
+/// Drop `spec.replicas` from a Deployment, so that it's neither diffed nor applied
+/// over the value an autoscaler has set. Leaving it out of the apply hands ownership to the HPA.
+pub fn strip_replicas(s: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut deployment: Deployment = SERDE.from_str(str::from_utf8(s)?)?;
+    if let Some(spec) = &mut deployment.spec {
+        spec.replicas = None;
+    }
+    Ok(SERDE.to_string(&deployment)?.into_bytes())
+}
+
+fn immutable_warning(kind: &str, changed: &[String]) -> String {
+    if changed.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\nWARNING: immutable field(s) changed: {}. The API server will reject this change; \
+         delete and recreate the {} to apply it.\n",
+        changed.join(", "),
+        kind
+    )
 }
 
 impl K8sConnector {
@@ -87,7 +277,7 @@ impl K8sConnector {
         let mut res = Vec::new();
         let addr = K8sClusterAddress::from_path(addr)?;
 
-        let op = match addr.res_addr {
+        let op = match addr.res_addr.clone() {
             K8sResourceAddress::Namespace(name) => {
                 create_delete_patch!(Namespace, name, current, desired)
             }
@@ -98,14 +288,69 @@ impl K8sConnector {
                 create_delete_patch!(Service, namespace, name, current, desired)
             }
             K8sResourceAddress::Deployment(namespace, name) => {
-                create_delete_patch!(Deployment, namespace, name, current, desired)
+                if current.is_some() && self.deployment_is_autoscaled(&addr.cluster, &namespace, &name).await {
+                    // The HPA owns spec.replicas, so drift there is the autoscaler at work, not a change.
+                    let current = current.as_deref().map(strip_replicas).transpose()?;
+                    let desired = desired.as_deref().map(strip_replicas).transpose()?;
+                    if current == desired {
+                        None
+                    } else {
+                        create_delete_patch!(Deployment, namespace, name, current, desired)
+                    }
+                } else {
+                    create_delete_patch!(Deployment, namespace, name, current, desired)
+                }
+            }
+            K8sResourceAddress::StatefulSet(namespace, name) => {
+                create_delete_patch!(StatefulSet, namespace, name, current, desired, immutable: STATEFULSET_IMMUTABLE_FIELDS)
+            }
+            K8sResourceAddress::DaemonSet(namespace, name) => {
+                create_delete_patch!(DaemonSet, namespace, name, current, desired)
+            }
+            K8sResourceAddress::ReplicaSet(namespace, name) => {
+                create_delete_patch!(ReplicaSet, namespace, name, current, desired)
+            }
+            K8sResourceAddress::Job(namespace, name) => {
+                create_delete_patch!(Job, namespace, name, current, desired, replace_on: JOB_REPLACE_FIELDS)
+            }
+            K8sResourceAddress::CronJob(namespace, name) => {
+                create_delete_patch!(CronJob, namespace, name, current, desired)
             }
             K8sResourceAddress::ConfigMap(namespace, name) => {
                 create_delete_patch!(ConfigMap, namespace, name, current, desired)
             }
-            // K8sResourceAddress::Secret(namespace, name) => {
-            //     create_delete_patch!(Secret, namespace, name, current, desired)
-            // }
+            K8sResourceAddress::Ingress(namespace, name) => {
+                create_delete_patch!(Ingress, namespace, name, current, desired)
+            }
+            K8sResourceAddress::NetworkPolicy(namespace, name) => {
+                create_delete_patch!(NetworkPolicy, namespace, name, current, desired)
+            }
+            K8sResourceAddress::ResourceQuota(namespace, name) => {
+                let op = create_delete_patch!(ResourceQuota, namespace, name, current, desired);
+                let warnings = self.quota_usage_warnings(&addr.cluster, &namespace, &name, &desired).await;
+                op.map(|mut op| {
+                    if !warnings.is_empty() {
+                        let message = op.friendly_message.get_or_insert_default();
+                        message.push_str(&format!("\nWARNING: quota is below current usage:\n{}", warnings.join("\n")));
+                    }
+                    op
+                })
+            }
+            K8sResourceAddress::LimitRange(namespace, name) => {
+                create_delete_patch!(LimitRange, namespace, name, current, desired)
+            }
+            K8sResourceAddress::ServiceAccount(namespace, name) => {
+                create_delete_patch!(ServiceAccount, namespace, name, current, desired)
+            }
+            K8sResourceAddress::HorizontalPodAutoscaler(namespace, name) => {
+                create_delete_patch!(HorizontalPodAutoscaler, namespace, name, current, desired)
+            }
+            K8sResourceAddress::PodDisruptionBudget(namespace, name) => {
+                create_delete_patch!(PodDisruptionBudget, namespace, name, current, desired)
+            }
+            K8sResourceAddress::Secret(namespace, name) => {
+                self.plan_secret(&namespace, &name, &current, &desired).await?
+            }
             K8sResourceAddress::PersistentVolumeClaim(namespace, name) => {
                 create_delete_patch!(PersistentVolumeClaim, namespace, name, current, desired)
             }
@@ -124,14 +369,46 @@ impl K8sConnector {
             K8sResourceAddress::ClusterRoleBinding(name) => {
                 create_delete_patch!(ClusterRoleBinding, name, current, desired)
             }
+            K8sResourceAddress::IngressClass(name) => {
+                create_delete_patch!(IngressClass, name, current, desired)
+            }
+            K8sResourceAddress::StorageClass(name) => {
+                create_delete_patch!(StorageClass, name, current, desired, replace_on: STORAGECLASS_REPLACE_FIELDS)
+            }
+            K8sResourceAddress::CSIDriver(name) => {
+                create_delete_patch!(CSIDriver, name, current, desired)
+            }
+            K8sResourceAddress::ValidatingWebhookConfiguration(name) => {
+                create_delete_patch!(ValidatingWebhookConfiguration, name, current, desired)
+            }
+            K8sResourceAddress::MutatingWebhookConfiguration(name) => {
+                create_delete_patch!(MutatingWebhookConfiguration, name, current, desired)
+            }
+            K8sResourceAddress::ValidatingAdmissionPolicy(name) => {
+                create_delete_patch!(ValidatingAdmissionPolicy, name, current, desired)
+            }
+            K8sResourceAddress::ValidatingAdmissionPolicyBinding(name) => {
+                create_delete_patch!(ValidatingAdmissionPolicyBinding, name, current, desired)
+            }
+            K8sResourceAddress::PriorityClass(name) => {
+                create_delete_patch!(PriorityClass, name, current, desired)
+            }
+            K8sResourceAddress::RuntimeClass(name) => {
+                create_delete_patch!(RuntimeClass, name, current, desired)
+            }
+            K8sResourceAddress::Node(name) => self.plan_node(&name, &current, &desired)?,
+            K8sResourceAddress::CustomResourceDefinition(name) => {
+                create_delete_patch!(CustomResourceDefinition, name, current, desired)
+            }
+            K8sResourceAddress::Bundle(name) => return self.plan_bundle(&addr.cluster, &name, &current, &desired).await,
+            K8sResourceAddress::Dynamic(..) | K8sResourceAddress::ClusterDynamic(..) => {
+                let client = self.get_or_init_client(&addr.cluster).await?;
+                self.plan_dynamic(&addr.cluster, &client, &addr.res_addr, &current, &desired).await?
+            }
             // K8sResourceAddress::Binding(_, _) => todo!(),
             // K8sResourceAddress::Endpoints(_, _) => todo!(),
-            // K8sResourceAddress::LimitRange(_, _) => todo!(),
-            // K8sResourceAddress::Node(_, _) => todo!(),
             // K8sResourceAddress::PodTemplate(_, _) => todo!(),
             // K8sResourceAddress::ReplicationController(_, _) => todo!(),
-            // K8sResourceAddress::ResourceQuota(_, _) => todo!(),
-            // K8sResourceAddress::ServiceAccount(_, _) => todo!(),
         };
 
         if let Some(op) = op {
@@ -141,3 +418,45 @@ impl K8sConnector {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::check_eq;
+
+    const DEPLOYMENT: &str = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  namespace: default
+spec:
+  replicas: 2
+  selector:
+    matchLabels:
+      app: web
+  template:
+    metadata:
+      labels:
+        app: web
+    spec:
+      containers:
+        - name: web
+          image: nginx
+"#;
+
+    #[test]
+    fn strip_replicas_ignores_scaling() {
+        let declared = DEPLOYMENT.as_bytes();
+        let scaled = DEPLOYMENT.replace("replicas: 2", "replicas: 7");
+        let scaled = scaled.as_bytes();
+
+        assert!(!check_eq::<Deployment>(declared, scaled).unwrap());
+
+        let declared = strip_replicas(declared).unwrap();
+        assert!(check_eq::<Deployment>(&declared, &strip_replicas(scaled).unwrap()).unwrap());
+
+        let changed = DEPLOYMENT.replace("image: nginx", "image: nginx:1.27");
+        assert!(!check_eq::<Deployment>(&declared, &strip_replicas(changed.as_bytes()).unwrap()).unwrap());
+    }
+}
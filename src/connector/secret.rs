@@ -0,0 +1,282 @@
+use std::path::PathBuf;
+
+use anyhow::bail;
+use autoschematic_core::{
+    connector::{GetResourceResponse, OpExecResponse, PlanResponseElement, ResourceAddress},
+    connector_op,
+    diag::{Diagnostic, DiagnosticPosition, DiagnosticResponse, DiagnosticSeverity, DiagnosticSpan},
+};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    Api, Client,
+    api::{DeleteParams, ListParams, PatchParams, PostParams},
+    runtime::reflector::Lookup,
+};
+use serde_yaml::Value;
+
+use crate::{
+    addr::{K8sClusterAddress, K8sResourceAddress},
+    config::{CONFIG_PATH, K8sImportFilter},
+    neat::neatify_resource,
+    op::K8sConnectorOp,
+    secret::{SecretKey, SecretKeyDiff, decrypt_secret, encrypt_secret, plaintext_entries, strip_secret_values},
+    util::{SERDE, check_syntax, diff_yaml_values, from_str_option},
+};
+
+use super::{K8sConnector, SerdeBackend, list::list_metadata_paged};
+
+/// Secret types that are generated by controllers rather than authored, and so are never imported.
+const GENERATED_SECRET_TYPES: &[&str] = &["kubernetes.io/service-account-token", "helm.sh/release.v1"];
+
+/// Flag any Secret value that isn't encrypted. Values themselves are checked only once decrypted at plan time.
+pub fn diag_secret(backend: SerdeBackend, a: &[u8]) -> anyhow::Result<Option<DiagnosticResponse>> {
+    if let Some(diag) = check_syntax::<Value>(backend, a)? {
+        return Ok(Some(diag));
+    }
+
+    let s = str::from_utf8(a)?;
+    let v: Value = backend.from_str(s)?;
+
+    let diagnostics: Vec<Diagnostic> = plaintext_entries(&v)
+        .into_iter()
+        .map(|(field, key)| {
+            let position = key_position(s, field, &key);
+            Diagnostic {
+                severity: DiagnosticSeverity::Error as u8,
+                span: DiagnosticSpan {
+                    start: position.clone(),
+                    end: position,
+                },
+                message: format!(
+                    "{}.{} is not encrypted; Secret values must be stored as ENC[age,...]",
+                    field, key
+                ),
+            }
+        })
+        .collect();
+
+    if diagnostics.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(DiagnosticResponse { diagnostics }))
+}
+
+/// Where `key` is declared under `field` in a Secret file, found by scanning for
+/// `key:` (or `"key":`) on the lines after `field`'s own.
+fn key_position(s: &str, field: &str, key: &str) -> DiagnosticPosition {
+    let declares = |line: &str, name: &str| {
+        let line = line.trim_start();
+        line.starts_with(&format!("{}:", name)) || line.starts_with(&format!("\"{}\":", name))
+    };
+
+    let lines: Vec<&str> = s.lines().collect();
+    let field_line = lines.iter().position(|line| declares(line, field)).unwrap_or(0);
+    let Some(key_line) = lines.iter().skip(field_line + 1).position(|line| declares(line, key)) else {
+        return DiagnosticPosition { line: 1, col: 1 };
+    };
+    let line = field_line + 1 + key_line;
+
+    DiagnosticPosition {
+        line: line as u32 + 1,
+        col: (lines[line].len() - lines[line].trim_start().len()) as u32 + 1,
+    }
+}
+
+impl K8sConnector {
+    pub async fn secret_key(&self) -> anyhow::Result<SecretKey> {
+        let Some(key_file) = self.config.read().await.secret_key_file.clone() else {
+            bail!("Managing Secrets requires secret_key_file to be set in {}", CONFIG_PATH);
+        };
+        SecretKey::load(&self.prefix.join(key_file))
+    }
+
+    pub async fn secrets_enabled(&self) -> bool {
+        self.config.read().await.secret_key_file.is_some()
+    }
+
+    pub async fn list_secrets(
+        &self,
+        cluster: &str,
+        client: &Client,
+        namespace: &str,
+        import_filter: &K8sImportFilter,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut res = Vec::new();
+
+        let field_selector = GENERATED_SECRET_TYPES
+            .iter()
+            .map(|t| format!("type!={}", t))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), namespace);
+        for secret in list_metadata_paged(&secrets, &ListParams::default().fields(&field_selector)).await? {
+            let Some(name) = secret.name() else { continue };
+            if !import_filter.includes("Secret", &secret.metadata) {
+                continue;
+            }
+            res.push(
+                K8sClusterAddress {
+                    cluster: cluster.to_string(),
+                    res_addr: K8sResourceAddress::Secret(namespace.to_string(), name.to_string()),
+                }
+                .to_path_buf(),
+            );
+        }
+
+        Ok(res)
+    }
+
+    /// Fetch a Secret and encrypt its values, so that the imported file is safe to commit.
+    pub async fn get_secret(
+        &self,
+        client: &Client,
+        namespace: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<GetResourceResponse>> {
+        let key = self.secret_key().await?;
+
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), namespace);
+        let Ok(secret) = secrets.get(name).await else { return Ok(None) };
+
+        let mut v = serde_yaml::to_value(&secret)?;
+        neatify_resource(&mut v);
+        encrypt_secret(&mut v, &key)?;
+
+        Ok(Some(GetResourceResponse {
+            resource_definition: SERDE.to_string(&v)?.into_bytes(),
+            outputs: None,
+        }))
+    }
+
+    /// Decrypt a Secret file and check that it deserializes as a Secret.
+    pub fn decrypt_secret_file(&self, key: &SecretKey, s: &Option<Vec<u8>>) -> anyhow::Result<Option<Value>> {
+        let Some(mut v) = from_str_option::<Value>(s)? else {
+            return Ok(None);
+        };
+        decrypt_secret(&mut v, key)?;
+        let _: Secret = serde_yaml::from_value(v.clone())?;
+        Ok(Some(v))
+    }
+
+    /// Plan a Secret. Ops carry the still-encrypted file, and the plan message only
+    /// names the keys that changed, never their values.
+    pub async fn plan_secret(
+        &self,
+        namespace: &str,
+        name: &str,
+        current: &Option<Vec<u8>>,
+        desired: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Option<PlanResponseElement>> {
+        let key = self.secret_key().await?;
+
+        let current_v = self.decrypt_secret_file(&key, current)?;
+        let desired_v = self.decrypt_secret_file(&key, desired)?;
+
+        let op = match (current_v, desired_v, desired) {
+            (None, Some(desired_v), Some(desired)) => {
+                let key_diff = SecretKeyDiff::new(&Value::Null, &desired_v);
+                Some(connector_op!(
+                    K8sConnectorOp::Create(String::from_utf8(desired.clone())?),
+                    format!("Create Secret {}/{}:\n{}", namespace, name, key_diff)
+                ))
+            }
+
+            (Some(_), None, _) => Some(connector_op!(
+                K8sConnectorOp::Delete,
+                format!("Delete Secret {}/{}", namespace, name)
+            )),
+
+            (Some(mut current_v), Some(mut desired_v), Some(desired)) => {
+                let key_diff = SecretKeyDiff::new(&current_v, &desired_v);
+
+                strip_secret_values(&mut current_v);
+                strip_secret_values(&mut desired_v);
+                let diff = if current_v != desired_v {
+                    diff_yaml_values(&current_v, &desired_v)?
+                } else {
+                    String::new()
+                };
+
+                Some(connector_op!(
+                    K8sConnectorOp::Patch(String::from_utf8(desired.clone())?),
+                    format!("Modify Secret {}/{}:\n{}{}", namespace, name, diff, key_diff)
+                ))
+            }
+            _ => None,
+        };
+
+        Ok(op)
+    }
+
+    pub async fn op_exec_secret(
+        &self,
+        client: &Client,
+        namespace: &str,
+        name: &str,
+        op: K8sConnectorOp,
+    ) -> anyhow::Result<OpExecResponse> {
+        let api: Api<Secret> = Api::namespaced(client.clone(), namespace);
+
+        let patch_params = PatchParams {
+            field_manager: Some(String::from("autoschematic")),
+            ..Default::default()
+        };
+
+        let post_params = PostParams {
+            field_manager: Some(String::from("autoschematic")),
+            ..Default::default()
+        };
+
+        let output = match op {
+            K8sConnectorOp::Create(resource) => {
+                let key = self.secret_key().await?;
+                let Some(v) = self.decrypt_secret_file(&key, &Some(resource.into_bytes()))? else {
+                    bail!("Empty Secret definition for {}/{}", namespace, name);
+                };
+                let resource: Secret = serde_yaml::from_value(v)?;
+                api.create(&post_params, &resource).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Created Secret {}", name)),
+                }
+            }
+            K8sConnectorOp::Patch(resource) => {
+                let key = self.secret_key().await?;
+                let Some(v) = self.decrypt_secret_file(&key, &Some(resource.into_bytes()))? else {
+                    bail!("Empty Secret definition for {}/{}", namespace, name);
+                };
+                let resource: Secret = serde_yaml::from_value(v)?;
+                api.patch(name, &patch_params, &kube::api::Patch::Apply(resource)).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Modified Secret {}", name)),
+                }
+            }
+            K8sConnectorOp::Replace(_) => bail!("Secret {}/{} can be patched in place and is never replaced", namespace, name),
+            K8sConnectorOp::Delete => {
+                api.delete(name, &DeleteParams::default()).await?;
+                OpExecResponse {
+                    outputs: None,
+                    friendly_message: Some(format!("Deleted Secret {}", name)),
+                }
+            }
+        };
+
+        Ok(output)
+    }
+
+    /// Compare two Secret files by their decrypted contents, since encryption is randomized
+    /// and the same Secret never encrypts to the same bytes twice.
+    pub async fn secret_eq(&self, a: &[u8], b: &[u8]) -> anyhow::Result<bool> {
+        let key = self.secret_key().await?;
+
+        let a = self.decrypt_secret_file(&key, &Some(a.to_vec()))?;
+        let b = self.decrypt_secret_file(&key, &Some(b.to_vec()))?;
+
+        let a: Option<Secret> = a.map(serde_yaml::from_value).transpose()?;
+        let b: Option<Secret> = b.map(serde_yaml::from_value).transpose()?;
+
+        Ok(a == b)
+    }
+}
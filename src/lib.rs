@@ -1 +1,2 @@
-pub mod addr;
\ No newline at end of file
+pub mod addr;
+pub mod neat;
@@ -8,9 +8,11 @@ use autoschematic_core::{
 use connector::K8sConnector;
 
 pub mod addr;
+mod config;
 mod connector;
 mod resource;
 mod op;
+mod secret;
 mod op_impl;
 mod util;
 mod neat;
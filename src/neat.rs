@@ -73,7 +73,22 @@ pub fn neatify_resource(v: &mut Value) {
         }
     }
 
-    // 3) spec.template.metadata.annotations: drop rolling-hash/checksum noise
+    // 3) ServiceAccount token references populated by the token controller
+    if obj.get(&Value::from("kind")).and_then(Value::as_str) == Some("ServiceAccount") {
+        neatify_service_account(obj);
+    }
+
+    // 4) selector and labels the API server generates for a Job
+    if obj.get(&Value::from("kind")).and_then(Value::as_str) == Some("Job") {
+        neatify_job(obj);
+    }
+
+    // 5) caBundle injected by cert-manager's CA injector
+    if has_ca_injector_annotation(obj) {
+        neatify_injected_ca_bundle(obj);
+    }
+
+    // 6) spec.template.metadata.annotations: drop rolling-hash/checksum noise
     if let Some(spec) = obj.get_mut(&Value::from("spec")).and_then(Value::as_mapping_mut) {
         if let Some(tpl) = spec.get_mut(&Value::from("template")).and_then(Value::as_mapping_mut) {
             if let Some(tpl_meta) = tpl.get_mut(&Value::from("metadata")).and_then(Value::as_mapping_mut) {
@@ -105,3 +120,223 @@ pub fn neatify_resource(v: &mut Value) {
         }
     }
 }
+
+/// cert-manager's cainjector fills in `caBundle` on objects carrying one of these annotations.
+const CA_INJECTOR_ANNOTATIONS: [&str; 3] = [
+    "cert-manager.io/inject-ca-from",
+    "cert-manager.io/inject-ca-from-secret",
+    "cert-manager.io/inject-apiserver-ca",
+];
+
+fn has_ca_injector_annotation(obj: &Mapping) -> bool {
+    let Some(ann) = obj
+        .get(&Value::from("metadata"))
+        .and_then(|meta| meta.get("annotations"))
+        .and_then(Value::as_mapping)
+    else {
+        return false;
+    };
+    CA_INJECTOR_ANNOTATIONS.iter().any(|k| ann.contains_key(&Value::from(*k)))
+}
+
+/// Drop injected `caBundle`s: they're owned by cainjector and rotate with the certificate,
+/// so keeping them would only produce churn.
+/// Covers webhook configurations, CRD conversion webhooks and APIServices.
+fn neatify_injected_ca_bundle(obj: &mut Mapping) {
+    let ca_bundle = Value::from("caBundle");
+    let client_config = Value::from("clientConfig");
+
+    if let Some(webhooks) = obj.get_mut(&Value::from("webhooks")).and_then(Value::as_sequence_mut) {
+        for webhook in webhooks {
+            if let Some(cc) = webhook.get_mut(&client_config).and_then(Value::as_mapping_mut) {
+                cc.remove(&ca_bundle);
+            }
+        }
+    }
+
+    if let Some(spec) = obj.get_mut(&Value::from("spec")).and_then(Value::as_mapping_mut) {
+        spec.remove(&ca_bundle);
+
+        if let Some(cc) = spec
+            .get_mut(&Value::from("conversion"))
+            .and_then(|conversion| conversion.get_mut("webhook"))
+            .and_then(|webhook| webhook.get_mut(&client_config))
+            .and_then(Value::as_mapping_mut)
+        {
+            cc.remove(&ca_bundle);
+        }
+    }
+}
+
+/// Labels that the API server adds to a Job and its pod template, carrying the Job's UID.
+const JOB_GENERATED_LABELS: [&str; 4] = [
+    "controller-uid",
+    "batch.kubernetes.io/controller-uid",
+    "job-name",
+    "batch.kubernetes.io/job-name",
+];
+
+/// Drop the selector the API server generates for a Job, along with the labels it matches on.
+/// They embed the live Job's UID, so recreating the Job from them would be rejected
+/// unless `manualSelector` is set, in which case they're left alone.
+pub fn neatify_job(obj: &mut Mapping) {
+    let manual_selector = obj
+        .get(&Value::from("spec"))
+        .and_then(|spec| spec.get("manualSelector"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    if manual_selector {
+        return;
+    }
+
+    let strip_labels = |meta: &mut Mapping| {
+        if let Some(lbl) = meta.get_mut(&Value::from("labels")).and_then(Value::as_mapping_mut) {
+            for k in JOB_GENERATED_LABELS {
+                lbl.remove(&Value::from(k));
+            }
+            if lbl.is_empty() {
+                meta.remove(&Value::from("labels"));
+            }
+        }
+    };
+
+    if let Some(meta) = obj.get_mut(&Value::from("metadata")).and_then(Value::as_mapping_mut) {
+        strip_labels(meta);
+    }
+
+    let Some(spec) = obj.get_mut(&Value::from("spec")).and_then(Value::as_mapping_mut) else {
+        return;
+    };
+    spec.remove(&Value::from("selector"));
+
+    if let Some(tpl_meta) = spec
+        .get_mut(&Value::from("template"))
+        .and_then(|tpl| tpl.get_mut("metadata"))
+        .and_then(Value::as_mapping_mut)
+    {
+        strip_labels(tpl_meta);
+    }
+}
+
+/// Drop the `<sa>-token-xxxxx` / `<sa>-dockercfg-xxxxx` references that controllers add to
+/// a ServiceAccount's `secrets` and `imagePullSecrets`, keeping any the user added themselves.
+fn neatify_service_account(obj: &mut Mapping) {
+    let Some(sa_name) = obj
+        .get(&Value::from("metadata"))
+        .and_then(|meta| meta.get("name"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    else {
+        return;
+    };
+
+    let re_generated = Regex::new(&format!(r"^{}-(token|dockercfg)-[a-z0-9]{{5}}$", regex::escape(&sa_name))).unwrap();
+
+    for field in ["secrets", "imagePullSecrets"] {
+        let Some(refs) = obj.get_mut(&Value::from(field)).and_then(Value::as_sequence_mut) else {
+            continue;
+        };
+        refs.retain(|r| {
+            !r.get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|name| re_generated.is_match(name))
+        });
+        if refs.is_empty() {
+            obj.remove(&Value::from(field));
+        }
+    }
+}
+
+/// Whether a neatified ServiceAccount carries anything beyond its name and namespace,
+/// i.e. whether a namespace's auto-created `default` ServiceAccount has been customized.
+pub fn service_account_is_customized(v: &Value) -> bool {
+    let Some(obj) = v.as_mapping() else { return false };
+
+    let has_extra_fields = obj
+        .keys()
+        .filter_map(Value::as_str)
+        .any(|k| !matches!(k, "apiVersion" | "kind" | "metadata"));
+
+    let has_extra_metadata = obj
+        .get(&Value::from("metadata"))
+        .and_then(Value::as_mapping)
+        .is_some_and(|meta| {
+            meta.keys()
+                .filter_map(Value::as_str)
+                .any(|k| !matches!(k, "name" | "namespace"))
+        });
+
+    has_extra_fields || has_extra_metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::api::batch::v1::Job;
+
+    use super::*;
+
+    const LIVE_JOB: &str = r#"
+apiVersion: batch/v1
+kind: Job
+metadata:
+  name: migrate
+  namespace: default
+  uid: 6f1c2a3e-0000-4000-8000-000000000001
+  resourceVersion: "1234"
+  labels:
+    app: migrate
+    batch.kubernetes.io/controller-uid: 6f1c2a3e-0000-4000-8000-000000000001
+    batch.kubernetes.io/job-name: migrate
+    controller-uid: 6f1c2a3e-0000-4000-8000-000000000001
+    job-name: migrate
+spec:
+  backoffLimit: 6
+  selector:
+    matchLabels:
+      batch.kubernetes.io/controller-uid: 6f1c2a3e-0000-4000-8000-000000000001
+  template:
+    metadata:
+      labels:
+        app: migrate
+        batch.kubernetes.io/controller-uid: 6f1c2a3e-0000-4000-8000-000000000001
+        batch.kubernetes.io/job-name: migrate
+        controller-uid: 6f1c2a3e-0000-4000-8000-000000000001
+        job-name: migrate
+    spec:
+      restartPolicy: Never
+      containers:
+        - name: migrate
+          image: migrate:1.0
+status:
+  succeeded: 1
+"#;
+
+    #[test]
+    fn neat_job_is_creatable() {
+        let mut v: Value = serde_yaml::from_str(LIVE_JOB).unwrap();
+        neatify_resource(&mut v);
+        let job: Job = serde_yaml::from_value(v).unwrap();
+
+        // Without manualSelector, the API server rejects a Job that carries a selector or controller-uid labels.
+        let spec = job.spec.unwrap();
+        assert_eq!(spec.manual_selector, None);
+        assert_eq!(spec.selector, None);
+        let only_app = Some(BTreeMap::from([(String::from("app"), String::from("migrate"))]));
+        assert_eq!(spec.template.metadata.unwrap().labels, only_app);
+        assert_eq!(job.metadata.labels, only_app);
+        assert_eq!(job.metadata.uid, None);
+        assert!(job.status.is_none());
+    }
+
+    #[test]
+    fn neat_job_keeps_manual_selector() {
+        let mut v: Value = serde_yaml::from_str(LIVE_JOB).unwrap();
+        v["spec"]["manualSelector"] = Value::from(true);
+        neatify_resource(&mut v);
+
+        assert!(v["spec"].get("selector").is_some());
+        assert!(v["spec"]["template"]["metadata"]["labels"].get("controller-uid").is_some());
+    }
+}
@@ -1,22 +1,148 @@
-use autoschematic_core::{connector::ConnectorOp, util::{PrettyConfig, RON}};
-use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use anyhow::bail;
+
+use autoschematic_core::{connector::ConnectorOp, util::RON};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::util::SERDE;
+
+/// The version of the serialized op format.
+/// Version 1 ops were unversioned RON, and carried their resources as RON.
+/// Version 2 ops, and the resources they carry, are YAML.
+const OP_FORMAT_VERSION: u32 = 2;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum K8sConnectorOp {
     Create(String),
     Patch(String),
+    /// Delete the object, wait for it to be gone, then create it again.
+    /// Used when a change touches fields the API server won't update in place.
+    Replace(String),
     Delete,
 }
 
+impl K8sConnectorOp {
+    /// Re-encode a version 1 op's RON resource as YAML.
+    fn upgrade_v1(self) -> anyhow::Result<Self> {
+        let upgrade = |resource: String| -> anyhow::Result<String> {
+            let v: serde_yaml::Value = RON.from_str(&resource)?;
+            SERDE.to_string(&v)
+        };
+
+        Ok(match self {
+            K8sConnectorOp::Create(resource) => K8sConnectorOp::Create(upgrade(resource)?),
+            K8sConnectorOp::Patch(resource) => K8sConnectorOp::Patch(upgrade(resource)?),
+            K8sConnectorOp::Replace(resource) => K8sConnectorOp::Replace(upgrade(resource)?),
+            K8sConnectorOp::Delete => K8sConnectorOp::Delete,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VersionedOp<T> {
+    version: u32,
+    op: T,
+}
+
+fn to_versioned_string<T: Serialize>(op: &T) -> anyhow::Result<String> {
+    SERDE.to_string(&VersionedOp {
+        version: OP_FORMAT_VERSION,
+        op,
+    })
+}
+
+/// Parse an op, falling back to the unversioned (version 1) RON format for plans serialized by older releases.
+/// Only input that isn't a YAML mapping with a `version` key is taken to be RON,
+/// so that a malformed versioned op reports its YAML error.
+fn from_versioned_str<T: DeserializeOwned>(s: &str) -> anyhow::Result<(u32, T)> {
+    let versioned = SERDE
+        .from_str::<serde_yaml::Value>(s)
+        .is_ok_and(|v| v.get("version").is_some());
+    if !versioned {
+        return Ok((1, RON.from_str(s)?));
+    }
+
+    let VersionedOp { version, op } = SERDE.from_str::<VersionedOp<T>>(s)?;
+    if version > OP_FORMAT_VERSION {
+        bail!(
+            "Op format version {} is newer than this connector supports ({})",
+            version,
+            OP_FORMAT_VERSION
+        );
+    }
+    Ok((version, op))
+}
+
 impl ConnectorOp for K8sConnectorOp {
     fn to_string(&self) -> Result<String, anyhow::Error> {
-        Ok(RON.to_string_pretty(&self, PrettyConfig::default())?)
+        to_versioned_string(self)
+    }
+
+    fn from_str(s: &str) -> Result<Self, anyhow::Error>
+    where
+        Self: Sized,
+    {
+        match from_versioned_str(s)? {
+            (1, op) => K8sConnectorOp::upgrade_v1(op),
+            (_, op) => Ok(op),
+        }
+    }
+}
+
+/// An op on one object in a multi-document bundle file, addressed by that object's own path.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct K8sBundleOp {
+    pub member: PathBuf,
+    pub op: K8sConnectorOp,
+}
+
+impl ConnectorOp for K8sBundleOp {
+    fn to_string(&self) -> Result<String, anyhow::Error> {
+        to_versioned_string(self)
     }
 
     fn from_str(s: &str) -> Result<Self, anyhow::Error>
     where
         Self: Sized,
     {
-        Ok(RON.from_str(s)?)
+        match from_versioned_str(s)? {
+            (1, K8sBundleOp { member, op }) => Ok(K8sBundleOp {
+                member,
+                op: op.upgrade_v1()?,
+            }),
+            (_, op) => Ok(op),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_ron_ops_are_upgraded() {
+        let op = K8sConnectorOp::from_str(r#"Create("(kind: \"ConfigMap\", data: {\"a\": \"b\"})")"#).unwrap();
+        let K8sConnectorOp::Create(resource) = op else {
+            panic!("expected a Create op, got {:?}", op);
+        };
+        let v: serde_yaml::Value = serde_yaml::from_str(&resource).unwrap();
+        assert_eq!(v["kind"], serde_yaml::Value::from("ConfigMap"));
+        assert_eq!(v["data"]["a"], serde_yaml::Value::from("b"));
+    }
+
+    #[test]
+    fn versioned_ops_round_trip() {
+        let op = K8sConnectorOp::Patch(String::from("kind: ConfigMap\n"));
+        let parsed = K8sConnectorOp::from_str(&op.to_string().unwrap()).unwrap();
+        assert!(matches!(parsed, K8sConnectorOp::Patch(resource) if resource == "kind: ConfigMap\n"));
+    }
+
+    #[test]
+    fn malformed_versioned_op_reports_yaml_error() {
+        let err = K8sConnectorOp::from_str("version: 2\nop:\n  Frobnicate: x\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Frobnicate"), "{}", err);
     }
 }
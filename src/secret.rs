@@ -0,0 +1,276 @@
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use anyhow::{Context, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde_yaml::Value;
+
+/// Encrypted values are stored in Secret files as `ENC[age,<base64 ciphertext>]`.
+const ENC_PREFIX: &str = "ENC[age,";
+const ENC_SUFFIX: &str = "]";
+
+/// The age identity used to encrypt Secret values at rest in the repo.
+pub struct SecretKey {
+    identity: age::x25519::Identity,
+}
+
+impl SecretKey {
+    /// Load an age identity file, as written by `age-keygen`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let key_file =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read secret key file {}", path.display()))?;
+
+        let Some(key_line) = key_file
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+        else {
+            bail!("Secret key file {} does not contain an age identity", path.display());
+        };
+
+        let identity = age::x25519::Identity::from_str(key_line)
+            .map_err(|e| anyhow!("Invalid age identity in {}: {}", path.display(), e))?;
+
+        Ok(Self { identity })
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> anyhow::Result<String> {
+        let ciphertext = age::encrypt(&self.identity.to_public(), plaintext)?;
+        Ok(format!("{}{}{}", ENC_PREFIX, BASE64.encode(ciphertext), ENC_SUFFIX))
+    }
+
+    pub fn decrypt(&self, value: &str) -> anyhow::Result<Vec<u8>> {
+        let Some(encoded) = value.strip_prefix(ENC_PREFIX).and_then(|s| s.strip_suffix(ENC_SUFFIX)) else {
+            bail!("Value is not an encrypted ENC[age,...] envelope");
+        };
+        let ciphertext = BASE64.decode(encoded)?;
+        Ok(age::decrypt(&self.identity, &ciphertext)?)
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENC_PREFIX) && value.ends_with(ENC_SUFFIX)
+}
+
+/// The `data` and `stringData` entries of a Secret file that aren't encrypted, as `(field, key)`.
+pub fn plaintext_entries(v: &Value) -> Vec<(&'static str, String)> {
+    let mut entries = Vec::new();
+    for field in ["data", "stringData"] {
+        let Some(map) = v.get(field).and_then(Value::as_mapping) else { continue };
+        for (k, value) in map {
+            if !value.as_str().is_some_and(is_encrypted) {
+                entries.push((field, k.as_str().unwrap_or_default().to_string()));
+            }
+        }
+    }
+    entries
+}
+
+/// Encrypt every value under `data` and `stringData` in a Secret, in place.
+/// Live `data` values are base64, so they're decoded first and the raw bytes are encrypted.
+/// Values that are already encrypted are left as they are.
+pub fn encrypt_secret(v: &mut Value, key: &SecretKey) -> anyhow::Result<()> {
+    if let Some(data) = v.get_mut("data").and_then(Value::as_mapping_mut) {
+        for (k, value) in data.iter_mut() {
+            let Some(s) = value.as_str() else {
+                bail!("Secret value data.{} is not a string", k.as_str().unwrap_or_default());
+            };
+            if is_encrypted(s) {
+                continue;
+            }
+            let plaintext = BASE64.decode(s)?;
+            *value = Value::from(key.encrypt(&plaintext)?);
+        }
+    }
+
+    if let Some(string_data) = v.get_mut("stringData").and_then(Value::as_mapping_mut) {
+        for (k, value) in string_data.iter_mut() {
+            let Some(s) = value.as_str() else {
+                bail!("Secret value stringData.{} is not a string", k.as_str().unwrap_or_default());
+            };
+            if is_encrypted(s) {
+                continue;
+            }
+            *value = Value::from(key.encrypt(s.as_bytes())?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decrypt every value under `data` and `stringData` in a Secret, in place,
+/// restoring `data` values to base64 so the result deserializes as a `Secret`.
+/// Fails if any value is in plaintext, so that a Secret is never applied from an unencrypted file.
+pub fn decrypt_secret(v: &mut Value, key: &SecretKey) -> anyhow::Result<()> {
+    let plaintext = plaintext_entries(v);
+    if !plaintext.is_empty() {
+        let names: Vec<String> = plaintext.iter().map(|(field, k)| format!("{}.{}", field, k)).collect();
+        bail!(
+            "Secret values must be encrypted as {}...{}, but these are in plaintext: {}",
+            ENC_PREFIX,
+            ENC_SUFFIX,
+            names.join(", ")
+        );
+    }
+
+    if let Some(data) = v.get_mut("data").and_then(Value::as_mapping_mut) {
+        for (_, value) in data.iter_mut() {
+            let Some(s) = value.as_str() else { continue };
+            *value = Value::from(BASE64.encode(key.decrypt(s)?));
+        }
+    }
+
+    if let Some(string_data) = v.get_mut("stringData").and_then(Value::as_mapping_mut) {
+        for (_, value) in string_data.iter_mut() {
+            let Some(s) = value.as_str() else { continue };
+            *value = Value::from(String::from_utf8(key.decrypt(s)?)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Which keys differ between two decrypted Secrets, across both `data` and `stringData`.
+/// Only key names are returned, never values, so the result is safe to print in a plan.
+#[derive(Debug, Default)]
+pub struct SecretKeyDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl SecretKeyDiff {
+    pub fn new(current: &Value, desired: &Value) -> Self {
+        let current = secret_entries(current);
+        let desired = secret_entries(desired);
+
+        let mut diff = SecretKeyDiff::default();
+
+        for (k, v) in &desired {
+            match current.get(k) {
+                None => diff.added.push(k.clone()),
+                Some(current_v) if current_v != v => diff.changed.push(k.clone()),
+                Some(_) => {}
+            }
+        }
+
+        for k in current.keys() {
+            if !desired.contains_key(k) {
+                diff.removed.push(k.clone());
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl std::fmt::Display for SecretKeyDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (label, keys) in [("added", &self.added), ("removed", &self.removed), ("changed", &self.changed)] {
+            if !keys.is_empty() {
+                writeln!(f, "  {} keys: {}", label, keys.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Flatten `data` and `stringData` into one map of key -> plaintext bytes.
+fn secret_entries(v: &Value) -> BTreeMap<String, Vec<u8>> {
+    let mut entries = BTreeMap::new();
+
+    if let Some(data) = v.get("data").and_then(Value::as_mapping) {
+        for (k, value) in data {
+            let (Some(k), Some(s)) = (k.as_str(), value.as_str()) else { continue };
+            entries.insert(k.to_string(), BASE64.decode(s).unwrap_or_else(|_| s.as_bytes().to_vec()));
+        }
+    }
+
+    if let Some(string_data) = v.get("stringData").and_then(Value::as_mapping) {
+        for (k, value) in string_data {
+            let (Some(k), Some(s)) = (k.as_str(), value.as_str()) else { continue };
+            entries.insert(k.to_string(), s.as_bytes().to_vec());
+        }
+    }
+
+    entries
+}
+
+/// Remove `data` and `stringData` from a Secret, leaving only fields that are safe to diff in the clear.
+pub fn strip_secret_values(v: &mut Value) {
+    if let Some(obj) = v.as_mapping_mut() {
+        obj.remove(&Value::from("data"));
+        obj.remove(&Value::from("stringData"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> SecretKey {
+        SecretKey {
+            identity: age::x25519::Identity::generate(),
+        }
+    }
+
+    const SECRET: &str = r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: db
+  namespace: default
+data:
+  password: aHVudGVyMg==
+stringData:
+  username: admin
+"#;
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let key = test_key();
+        let original: Value = serde_yaml::from_str(SECRET).unwrap();
+
+        let mut v = original.clone();
+        encrypt_secret(&mut v, &key).unwrap();
+        assert!(plaintext_entries(&v).is_empty());
+        assert!(!serde_yaml::to_string(&v).unwrap().contains("admin"));
+
+        decrypt_secret(&mut v, &key).unwrap();
+        assert_eq!(v, original);
+    }
+
+    #[test]
+    fn decrypt_rejects_plaintext() {
+        let key = test_key();
+        let mut v: Value = serde_yaml::from_str(SECRET).unwrap();
+        v["data"]["password"] = Value::from(key.encrypt(b"hunter2").unwrap());
+
+        let err = decrypt_secret(&mut v, &key).unwrap_err().to_string();
+        assert!(err.contains("stringData.username"));
+        assert!(!err.contains("admin"));
+        assert_eq!(plaintext_entries(&v), vec![("stringData", String::from("username"))]);
+    }
+
+    #[test]
+    fn key_diff_never_prints_values() {
+        let current: Value = serde_yaml::from_str(SECRET).unwrap();
+        let mut desired = current.clone();
+        desired["data"]["password"] = Value::from(BASE64.encode("correct-horse"));
+        desired["stringData"]["token"] = Value::from("s3cr3t-token");
+        desired["stringData"].as_mapping_mut().unwrap().remove("username");
+
+        let diff = SecretKeyDiff::new(&current, &desired);
+        assert_eq!(diff.added, vec![String::from("token")]);
+        assert_eq!(diff.removed, vec![String::from("username")]);
+        assert_eq!(diff.changed, vec![String::from("password")]);
+
+        let printed = diff.to_string();
+        for value in ["hunter2", "aHVudGVyMg==", "correct-horse", "s3cr3t-token", "admin"] {
+            assert!(!printed.contains(value), "{} leaked into {}", value, printed);
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use autoschematic_core::{
     connector::GetResourceResponse,
+    diag::{Diagnostic, DiagnosticPosition, DiagnosticResponse, DiagnosticSeverity, DiagnosticSpan},
     util::{PrettyConfig, RON},
 };
 use kube::api::ObjectMeta;
 use ron::de;
 use serde::{Deserialize, Serialize};
+use similar::TextDiff;
 
 use crate::{connector::SerdeBackend, neat::neatify_resource};
 
@@ -30,6 +32,175 @@ where
     }
 }
 
+/// A unified diff between two values, as they'd appear in a resource file.
+pub fn diff_yaml_values<T: Serialize>(current: &T, desired: &T) -> anyhow::Result<String> {
+    let current = SERDE.to_string(current)?;
+    let desired = SERDE.to_string(desired)?;
+    Ok(TextDiff::from_lines(&current, &desired)
+        .unified_diff()
+        .context_radius(3)
+        .to_string())
+}
+
+/// Paths from the root of a resource to its quantity fields, where `*` matches any map key
+/// and `[]` any list item: `ResourceQuota.spec.hard`, `LimitRange.spec.limits`,
+/// `PersistentVolume.spec.capacity` and `RuntimeClass.overhead.podFixed`.
+const QUANTITY_PATHS: &[&[&str]] = &[
+    &["spec", "hard", "*"],
+    &["spec", "limits", "[]", "max", "*"],
+    &["spec", "limits", "[]", "min", "*"],
+    &["spec", "limits", "[]", "default", "*"],
+    &["spec", "limits", "[]", "defaultRequest", "*"],
+    &["spec", "limits", "[]", "maxLimitRequestRatio", "*"],
+    &["spec", "capacity", "*"],
+    &["overhead", "podFixed", "*"],
+];
+
+/// Quantity fields that can appear at any depth, such as a container's resources
+/// (wherever its pod template is nested) or a PersistentVolumeClaim's storage request.
+const QUANTITY_PATH_SUFFIXES: &[&[&str]] = &[
+    &["resources", "limits", "*"],
+    &["resources", "requests", "*"],
+    &["emptyDir", "sizeLimit"],
+];
+
+/// Port fields, which may be written as either a number or a numeric string: Service, container
+/// and NetworkPolicy ports, probe and lifecycle handlers, and Ingress backends.
+const PORT_PATH_SUFFIXES: &[&[&str]] = &[
+    &["ports", "[]", "port"],
+    &["ports", "[]", "targetPort"],
+    &["ports", "[]", "nodePort"],
+    &["ports", "[]", "containerPort"],
+    &["ports", "[]", "hostPort"],
+    &["httpGet", "port"],
+    &["tcpSocket", "port"],
+    &["grpc", "port"],
+    &["service", "port", "number"],
+];
+
+fn path_matches(path: &[String], pattern: &[&str]) -> bool {
+    path.len() == pattern.len() && path.iter().zip(pattern).all(|(k, p)| *p == "*" || k == p)
+}
+
+fn path_ends_with(path: &[String], suffixes: &[&[&str]]) -> bool {
+    suffixes
+        .iter()
+        .any(|suffix| path.len() >= suffix.len() && path_matches(&path[path.len() - suffix.len()..], suffix))
+}
+
+fn is_quantity_path(path: &[String]) -> bool {
+    QUANTITY_PATHS.iter().any(|pattern| path_matches(path, pattern)) || path_ends_with(path, QUANTITY_PATH_SUFFIXES)
+}
+
+/// Rewrite a resource file's raw value so that equivalent spellings of the same value compare equal:
+/// quantities are rewritten in plain decimal (`1000m` -> `1`), numeric port strings become numbers,
+/// and null or empty maps and lists are dropped, as the API server omits them.
+/// `path` is the list of keys leading to `v`, with `[]` standing for a list item.
+fn normalize_value(v: &mut serde_yaml::Value, path: &mut Vec<String>) {
+    use serde_yaml::Value;
+
+    match v {
+        Value::Mapping(map) => {
+            for (k, child) in map.iter_mut() {
+                path.push(k.as_str().unwrap_or_default().to_string());
+                normalize_value(child, path);
+                path.pop();
+            }
+            map.retain(|_, child| match child {
+                Value::Null => false,
+                Value::Mapping(m) => !m.is_empty(),
+                Value::Sequence(s) => !s.is_empty(),
+                _ => true,
+            });
+        }
+        Value::Sequence(seq) => {
+            for child in seq.iter_mut() {
+                path.push(String::from("[]"));
+                normalize_value(child, path);
+                path.pop();
+            }
+        }
+        // Quantities stay strings, so that they still deserialize as `Quantity`.
+        Value::String(s) if is_quantity_path(path) => {
+            if let Some(q) = parse_quantity(s) {
+                *v = Value::from(q.to_string());
+            }
+        }
+        Value::Number(n) if is_quantity_path(path) => {
+            if let Some(q) = n.as_f64() {
+                *v = Value::from(q.to_string());
+            }
+        }
+        Value::String(s) if path_ends_with(path, PORT_PATH_SUFFIXES) => {
+            if let Ok(port) = s.parse::<i64>() {
+                *v = Value::from(port);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Compare two resource files as `T`, ignoring differences in how equivalent values are written
+/// (see [`normalize_value`]). Each file is normalized before it's parsed as `T`,
+/// so that e.g. a quoted `"80"` is accepted for an integer port.
+pub fn check_eq<T>(a: &[u8], b: &[u8]) -> anyhow::Result<bool>
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    fn normalized<T>(s: &[u8]) -> anyhow::Result<serde_yaml::Value>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        let mut v: serde_yaml::Value = SERDE.from_str(str::from_utf8(s)?)?;
+        normalize_value(&mut v, &mut Vec::new());
+        let t: T = serde_yaml::from_value(v)?;
+        Ok(serde_yaml::to_value(&t)?)
+    }
+
+    Ok(normalized::<T>(a)? == normalized::<T>(b)?)
+}
+
+/// Parse a resource file as `T` with the given backend, reporting a parse error as a diagnostic at its location.
+pub fn check_syntax<T>(backend: SerdeBackend, a: &[u8]) -> anyhow::Result<Option<DiagnosticResponse>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let s = str::from_utf8(a)?;
+
+    let (message, line, col) = match backend {
+        SerdeBackend::YAML => match serde_yaml::from_str::<T>(s) {
+            Ok(_) => return Ok(None),
+            Err(e) => {
+                let (line, col) = e.location().map(|l| (l.line(), l.column())).unwrap_or((1, 1));
+                (e.to_string(), line, col)
+            }
+        },
+        SerdeBackend::JSON => match serde_json::from_str::<T>(s) {
+            Ok(_) => return Ok(None),
+            Err(e) => (e.to_string(), e.line(), e.column()),
+        },
+        SerdeBackend::RON => match RON.from_str::<T>(s) {
+            Ok(_) => return Ok(None),
+            Err(e) => (e.code.to_string(), e.position.line, e.position.col),
+        },
+    };
+
+    let position = DiagnosticPosition {
+        line: line as u32,
+        col: col as u32,
+    };
+    Ok(Some(DiagnosticResponse {
+        diagnostics: vec![Diagnostic {
+            severity: DiagnosticSeverity::Error as u8,
+            span: DiagnosticSpan {
+                start: position.clone(),
+                end: position,
+            },
+            message,
+        }],
+    }))
+}
+
 pub fn get_ser_resource_output<T: Serialize>(t: &T) -> anyhow::Result<Option<GetResourceResponse>> {
 
     let mut v = serde_yaml::to_value(t)?;
@@ -40,3 +211,236 @@ pub fn get_ser_resource_output<T: Serialize>(t: &T) -> anyhow::Result<Option<Get
         outputs: None,
     }))
 }
+
+/// Parse a Kubernetes resource quantity such as "500m", "1.5Gi" or "2e3" into a plain number,
+/// so that quantities written with different suffixes can be compared.
+pub fn parse_quantity(q: &str) -> Option<f64> {
+    let q = q.trim();
+    let split = q
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(q.len());
+    let (number, suffix) = q.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024f64,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        "Ei" => 1024f64.powi(6),
+        exponent if exponent.starts_with(['e', 'E']) => 10f64.powi(exponent[1..].parse().ok()?),
+        _ => return None,
+    };
+
+    Some(number * multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::{
+        apps::v1::Deployment,
+        core::v1::{ConfigMap, LimitRange, PersistentVolume, Service},
+        rbac::v1::{ClusterRole, ClusterRoleBinding, Role},
+    };
+
+    use super::*;
+
+    #[test]
+    fn parse_quantity_suffixes() {
+        assert_eq!(parse_quantity("1000m"), Some(1.0));
+        assert_eq!(parse_quantity("250m"), Some(0.25));
+        assert_eq!(parse_quantity("1Gi"), Some(1024f64.powi(3)));
+        assert_eq!(parse_quantity("512Mi"), Some(512.0 * 1024f64.powi(2)));
+        assert_eq!(parse_quantity("1e3"), Some(1000.0));
+        assert_eq!(parse_quantity("2k"), Some(2000.0));
+        assert_eq!(parse_quantity("1.5"), Some(1.5));
+    }
+
+    #[test]
+    fn parse_quantity_invalid() {
+        for q in ["", "Gi", "abc", "1Xi", "1e", "1.2.3", "10 apples"] {
+            assert_eq!(parse_quantity(q), None, "{:?}", q);
+        }
+    }
+
+    fn deployment(cpu: &str, memory: &str) -> String {
+        format!(
+            r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  selector:
+    matchLabels:
+      app: web
+  template:
+    spec:
+      containers:
+        - name: web
+          image: web:1.0
+          resources:
+            limits:
+              cpu: {cpu}
+              memory: {memory}
+"#
+        )
+    }
+
+    #[test]
+    fn check_eq_quantities() {
+        let a = deployment("1000m", "1Gi");
+        let b = deployment("1", "\"1073741824\"");
+        assert!(check_eq::<Deployment>(a.as_bytes(), b.as_bytes()).unwrap());
+
+        let c = deployment("500m", "1Gi");
+        assert!(!check_eq::<Deployment>(a.as_bytes(), c.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn check_eq_limit_range_quantities() {
+        let limit_range = |default_cpu: &str| {
+            format!(
+                r#"
+apiVersion: v1
+kind: LimitRange
+metadata:
+  name: defaults
+spec:
+  limits:
+    - type: Container
+      default:
+        cpu: {default_cpu}
+"#
+            )
+        };
+        assert!(check_eq::<LimitRange>(limit_range("1000m").as_bytes(), limit_range("1").as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn check_eq_only_normalizes_quantity_paths() {
+        let config_map = |limit: &str| {
+            format!(
+                r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: settings
+data:
+  default: "{limit}"
+"#
+            )
+        };
+        // `default` is only a quantity inside a LimitRange's limits, so ConfigMap data is compared as written.
+        assert!(!check_eq::<ConfigMap>(config_map("1000m").as_bytes(), config_map("1").as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn check_eq_quoted_ports() {
+        let service = |port: &str| {
+            format!(
+                r#"
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+spec:
+  ports:
+    - port: {port}
+      targetPort: {port}
+"#
+            )
+        };
+        assert!(check_eq::<Service>(service("\"80\"").as_bytes(), service("80").as_bytes()).unwrap());
+        assert!(!check_eq::<Service>(service("\"80\"").as_bytes(), service("8080").as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn check_eq_keeps_port_named_config() {
+        let a = b"apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\ndata:\n  port: \"80\"\n";
+        assert!(check_eq::<ConfigMap>(a, a).unwrap());
+    }
+
+    #[test]
+    fn check_eq_empty_is_absent() {
+        let a = b"apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n  labels: {}\ndata: {}\n";
+        let b = b"apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n";
+        assert!(check_eq::<ConfigMap>(a, b).unwrap());
+    }
+
+    fn role(verbs: &str) -> String {
+        format!(
+            r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: Role
+metadata:
+  name: reader
+  namespace: payments
+rules:
+  - apiGroups: [""]
+    resources: [pods]
+    verbs: {}
+"#,
+            verbs
+        )
+    }
+
+    #[test]
+    fn check_eq_role_rules() {
+        let a = role("[get, list]");
+        assert!(check_eq::<Role>(a.as_bytes(), a.as_bytes()).unwrap());
+        assert!(!check_eq::<Role>(a.as_bytes(), role("[get, list, watch]").as_bytes()).unwrap());
+
+        let empty = b"apiVersion: rbac.authorization.k8s.io/v1\nkind: Role\nmetadata:\n  name: reader\nrules: []\n";
+        let absent = b"apiVersion: rbac.authorization.k8s.io/v1\nkind: Role\nmetadata:\n  name: reader\n";
+        assert!(check_eq::<Role>(empty, absent).unwrap());
+    }
+
+    #[test]
+    fn check_eq_cluster_rbac() {
+        let cluster_role = r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: node-reader
+rules:
+  - apiGroups: [""]
+    resources: [nodes]
+    verbs: [get]
+"#;
+        // RBAC kinds used to be compared as PersistentVolumes, which a ClusterRole never parses as.
+        assert!(serde_yaml::from_str::<PersistentVolume>(cluster_role).is_err());
+        assert!(check_eq::<ClusterRole>(cluster_role.as_bytes(), cluster_role.as_bytes()).unwrap());
+        let changed = cluster_role.replace("[get]", "[get, delete]");
+        assert!(!check_eq::<ClusterRole>(cluster_role.as_bytes(), changed.as_bytes()).unwrap());
+
+        let binding = r#"
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: node-reader
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: node-reader
+subjects:
+  - kind: Group
+    name: ops
+    apiGroup: rbac.authorization.k8s.io
+"#;
+        assert!(check_eq::<ClusterRoleBinding>(binding.as_bytes(), binding.as_bytes()).unwrap());
+        let rebound = binding.replace("name: ops", "name: dev");
+        assert!(!check_eq::<ClusterRoleBinding>(binding.as_bytes(), rebound.as_bytes()).unwrap());
+    }
+}